@@ -0,0 +1,45 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/// A small Bloom filter over string identifiers, backed by double hashing
+/// (Kirsch-Mitzenmacher) instead of `k` independent hash functions. Used to
+/// cheaply reject "definitely not present" lookups (spent tokens, tracked
+/// deposits) before falling back to an authoritative database check.
+pub struct BloomFilter {
+    bits: Vec<bool>,
+    hashes: u32,
+}
+
+impl BloomFilter {
+    pub fn new(bits: usize, hashes: u32) -> Self {
+        Self {
+            bits: vec![false; bits],
+            hashes,
+        }
+    }
+
+    fn positions(&self, identifier: &str) -> impl Iterator<Item = usize> + '_ {
+        let mut h1 = DefaultHasher::new();
+        identifier.hash(&mut h1);
+        let a = h1.finish();
+
+        let mut h2 = DefaultHasher::new();
+        (identifier, "bloom-salt").hash(&mut h2);
+        let b = h2.finish() | 1;
+
+        let len = self.bits.len();
+        (0..self.hashes).map(move |i| (a.wrapping_add((i as u64).wrapping_mul(b)) as usize) % len)
+    }
+
+    pub fn insert(&mut self, identifier: &str) {
+        for pos in self.positions(identifier) {
+            self.bits[pos] = true;
+        }
+    }
+
+    /// `false` means "definitely not present"; `true` means "maybe
+    /// present" and warrants an authoritative lookup.
+    pub fn might_contain(&self, identifier: &str) -> bool {
+        self.positions(identifier).all(|pos| self.bits[pos])
+    }
+}
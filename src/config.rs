@@ -28,10 +28,23 @@ pub struct Config {
     /// Admin npub (marketplace owner)
     pub admin_npub: String,
 
-    /// Cashu mint configuration
+    /// Primary Cashu mint configuration
     #[serde(default)]
     pub mint: MintConfig,
 
+    /// Additional mints to fall back to, in priority order, if the
+    /// primary mint is unhealthy or an operation against it fails. Lets an
+    /// operator spread float across mints and survive a single mint outage.
+    #[serde(default)]
+    pub additional_mints: Vec<MintConfig>,
+
+    /// Mint URLs trusted for cross-mint token acceptance, beyond the home
+    /// mint and `additional_mints`. A token issued by a mint not in this
+    /// list is rejected outright rather than attempting a melt/mint
+    /// bridge through it (see `CashuService::receive_tokens`).
+    #[serde(default)]
+    pub trusted_mints: Vec<String>,
+
     /// Lightning backend configuration
     #[serde(default)]
     pub lightning: LightningConfig,
@@ -44,6 +57,13 @@ pub struct Config {
     #[serde(default = "default_escrow_days")]
     pub escrow_days: u32,
 
+    /// Default resolution applied to a dispute whose `auto_resolve_at` has
+    /// passed with no admin action — a [`DisputeResolution`](crate::models::DisputeResolution)
+    /// string as accepted by `DisputeResolution::from_str` (e.g.
+    /// `"buyer_full"`, `"split_50_50"`).
+    #[serde(default = "default_dispute_timeout_resolution")]
+    pub dispute_timeout_resolution: String,
+
     /// Browsing fee in sats
     #[serde(default = "default_browsing_fee")]
     pub browsing_fee_sats: u64,
@@ -55,6 +75,116 @@ pub struct Config {
     /// Price lock duration in checkout (hours)
     #[serde(default = "default_price_lock_hours")]
     pub price_lock_hours: u32,
+
+    /// Nostr Wallet Connect (NIP-47) configuration
+    #[serde(default)]
+    pub nwc: NwcConfig,
+
+    /// BTC/fiat rate oracle configuration
+    #[serde(default)]
+    pub rate: RateConfig,
+
+    /// Escrow coordinator (P2PK handshake over Nostr DMs) configuration
+    #[serde(default)]
+    pub escrow_coordinator: EscrowCoordinatorConfig,
+
+    /// How often the background sweep (escrow auto-release, dispute
+    /// timeout, checkout price-lock expiry) ticks, in seconds
+    #[serde(default = "default_background_task_interval_secs")]
+    pub background_task_interval_secs: u64,
+
+    /// Domain tag value bound into NIP-42-style login challenge events, so
+    /// a challenge signed for this marketplace can't be replayed against a
+    /// different site the same way a NIP-98 request is bound to its `u`
+    /// (URL) tag.
+    #[serde(default = "default_auth_challenge_domain")]
+    pub auth_challenge_domain: String,
+
+    /// Whether `/login/dev` (raw-nsec login) is reachable at all.
+    /// Off by default — taking a user's nsec in an HTTP form body is the
+    /// exact exposure the challenge/response login flow exists to avoid,
+    /// so this only ever gets turned on for local development or tests.
+    #[serde(default = "default_dev_login_enabled")]
+    pub dev_login_enabled: bool,
+
+    /// Prior mean rating assumed for a seller before any reviews, used to
+    /// pull a seller's adjusted reputation score toward the mean when they
+    /// have few ratings (see `RatingService::reputation`).
+    #[serde(default = "default_rating_prior_mean")]
+    pub rating_prior_mean: f64,
+
+    /// Weight of the prior, expressed as a number of "phantom" ratings at
+    /// `rating_prior_mean` blended into every seller's adjusted score —
+    /// higher values require more real ratings before the adjusted score
+    /// tracks the raw average closely.
+    #[serde(default = "default_rating_prior_weight")]
+    pub rating_prior_weight: f64,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct NwcConfig {
+    /// Relay the wallet service listens on and publishes responses to
+    #[serde(default = "default_nwc_relay_url")]
+    pub relay_url: String,
+
+    /// Directory holding the wallet service's persisted NWC keypair
+    #[serde(default = "default_nwc_data_dir")]
+    pub data_dir: String,
+}
+
+impl Default for NwcConfig {
+    fn default() -> Self {
+        Self {
+            relay_url: default_nwc_relay_url(),
+            data_dir: default_nwc_data_dir(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct EscrowCoordinatorConfig {
+    /// Relay the coordinator publishes escrow handshake DMs to
+    #[serde(default = "default_escrow_coordinator_relay_url")]
+    pub relay_url: String,
+
+    /// Directory holding the coordinator's persisted arbiter keypair
+    #[serde(default = "default_escrow_coordinator_data_dir")]
+    pub data_dir: String,
+}
+
+impl Default for EscrowCoordinatorConfig {
+    fn default() -> Self {
+        Self {
+            relay_url: default_escrow_coordinator_relay_url(),
+            data_dir: default_escrow_coordinator_data_dir(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct RateConfig {
+    /// Rate API URL returning a BTC spot price (CoinGecko's simple-price
+    /// shape: `{"bitcoin": {"<currency>": <price>}}`)
+    #[serde(default = "default_rate_api_url")]
+    pub api_url: String,
+
+    /// Fiat currency code the rate is quoted in (e.g. "usd")
+    #[serde(default = "default_rate_currency")]
+    pub currency: String,
+
+    /// How long a fetched rate is cached before refetching
+    #[serde(default = "default_rate_cache_seconds")]
+    pub cache_seconds: u64,
+}
+
+impl Default for RateConfig {
+    fn default() -> Self {
+        Self {
+            api_url: default_rate_api_url(),
+            currency: default_rate_currency(),
+            cache_seconds: default_rate_cache_seconds(),
+        }
+    }
 }
 
 #[derive(Clone, Debug, Deserialize)]
@@ -160,6 +290,10 @@ fn default_escrow_days() -> u32 {
     10
 }
 
+fn default_dispute_timeout_resolution() -> String {
+    "buyer_full".to_string()
+}
+
 fn default_browsing_fee() -> u64 {
     100 // 100 sats
 }
@@ -168,6 +302,18 @@ fn default_price_lock_hours() -> u32 {
     3
 }
 
+fn default_background_task_interval_secs() -> u64 {
+    60
+}
+
+fn default_auth_challenge_domain() -> String {
+    "cashu-marketplace".to_string()
+}
+
+fn default_dev_login_enabled() -> bool {
+    false
+}
+
 fn default_mint_url() -> String {
     "https://mint.minibits.cash/Bitcoin".to_string()
 }
@@ -180,6 +326,34 @@ fn default_mint_unit() -> String {
     "sat".to_string()
 }
 
+fn default_nwc_relay_url() -> String {
+    "wss://relay.damus.io".to_string()
+}
+
+fn default_nwc_data_dir() -> String {
+    "data/nwc".to_string()
+}
+
+fn default_escrow_coordinator_relay_url() -> String {
+    "wss://relay.damus.io".to_string()
+}
+
+fn default_escrow_coordinator_data_dir() -> String {
+    "data/escrow-coordinator".to_string()
+}
+
+fn default_rate_api_url() -> String {
+    "https://api.coingecko.com/api/v3/simple/price?ids=bitcoin&vs_currencies=usd".to_string()
+}
+
+fn default_rate_currency() -> String {
+    "usd".to_string()
+}
+
+fn default_rate_cache_seconds() -> u64 {
+    60
+}
+
 fn default_lightning_backend() -> String {
     "lnbits".to_string()
 }
@@ -200,6 +374,14 @@ fn default_all_bond() -> u64 {
     600_000
 }
 
+fn default_rating_prior_mean() -> f64 {
+    3.0
+}
+
+fn default_rating_prior_weight() -> f64 {
+    5.0
+}
+
 impl Config {
     /// Load configuration from environment and config file
     pub fn load() -> anyhow::Result<Self> {
@@ -214,8 +396,20 @@ impl Config {
             .set_default("session_hours", default_session_hours())?
             .set_default("fee_percent", default_fee_percent())?
             .set_default("escrow_days", default_escrow_days())?
+            .set_default(
+                "dispute_timeout_resolution",
+                default_dispute_timeout_resolution(),
+            )?
             .set_default("browsing_fee_sats", default_browsing_fee())?
             .set_default("price_lock_hours", default_price_lock_hours())?
+            .set_default(
+                "background_task_interval_secs",
+                default_background_task_interval_secs(),
+            )?
+            .set_default("auth_challenge_domain", default_auth_challenge_domain())?
+            .set_default("dev_login_enabled", default_dev_login_enabled())?
+            .set_default("rating_prior_mean", default_rating_prior_mean())?
+            .set_default("rating_prior_weight", default_rating_prior_weight())?
             // Load from config file if exists
             .add_source(config::File::with_name("config").required(false))
             // Override with environment variables (MARKETPLACE_ prefix)
@@ -17,6 +17,18 @@ pub enum AppError {
     #[error("Session expired")]
     SessionExpired,
 
+    #[error("Invalid or expired refresh token")]
+    InvalidRefreshToken,
+
+    #[error("Refresh token reuse detected, session revoked")]
+    RefreshTokenReused,
+
+    #[error("Invalid or expired login challenge")]
+    InvalidLoginChallenge,
+
+    #[error("Invalid or expired access token")]
+    InvalidAccessToken,
+
     #[error("Not authenticated")]
     NotAuthenticated,
 
@@ -53,6 +65,12 @@ pub enum AppError {
     #[error("Order cannot be disputed")]
     OrderCannotBeDisputed,
 
+    #[error("Only a completed order can be rated")]
+    OrderNotRatable,
+
+    #[error("Order already rated")]
+    OrderAlreadyRated,
+
     // Cart errors
     #[error("Cart is empty")]
     CartEmpty,
@@ -60,9 +78,6 @@ pub enum AppError {
     #[error("Price lock expired")]
     PriceLockExpired,
 
-    #[error("Item already in cart")]
-    ItemAlreadyInCart,
-
     // Payment errors
     #[error("Insufficient balance: need {needed} sats, have {available} sats")]
     InsufficientBalanceDetails { needed: u64, available: u64 },
@@ -73,6 +88,9 @@ pub enum AppError {
     #[error("Invalid Cashu token")]
     InvalidCashuToken,
 
+    #[error("Invalid or tampered wallet backup")]
+    InvalidWalletBackup,
+
     #[error("Payment failed: {0}")]
     PaymentFailed(String),
 
@@ -83,11 +101,11 @@ pub enum AppError {
     #[error("Escrow not found")]
     EscrowNotFound,
 
-    #[error("Escrow already released")]
-    EscrowAlreadyReleased,
+    #[error("Illegal escrow transition: {from} -> {to}")]
+    InvalidEscrowTransition { from: String, to: String },
 
-    #[error("Escrow already refunded")]
-    EscrowAlreadyRefunded,
+    #[error("Escrow {escrow_id} ledger imbalance: {remaining} sats unaccounted for in its hold account")]
+    EscrowLedgerImbalance { escrow_id: String, remaining: i64 },
 
     // Dispute errors
     #[error("Dispute not found")]
@@ -109,6 +127,15 @@ pub enum AppError {
     #[error("Bond already paid for category")]
     BondAlreadyPaid,
 
+    #[error("Seller application not found")]
+    SellerApplicationNotFound,
+
+    #[error("A seller application is already pending for this account")]
+    SellerApplicationPending,
+
+    #[error("Seller application already decided")]
+    SellerApplicationAlreadyDecided,
+
     // Messaging errors
     #[error("Messaging disabled by seller")]
     MessagingDisabled,
@@ -167,19 +194,23 @@ impl IntoResponse for AppError {
         let (status, message) = match &self {
             // 400 Bad Request
             AppError::InvalidNsec
+            | AppError::InvalidLoginChallenge
             | AppError::InvalidCategory
             | AppError::InvalidCashuToken
+            | AppError::InvalidWalletBackup
             | AppError::InvalidResolution
             | AppError::InvalidDuration
             | AppError::InvalidBrowsingToken
             | AppError::MessageTooLong
-            | AppError::ItemAlreadyInCart
             | AppError::InvalidInput(_)
-            | AppError::BondAlreadyPaid => (StatusCode::BAD_REQUEST, self.to_string()),
+            | AppError::BondAlreadyPaid
+            | AppError::InvalidRefreshToken => (StatusCode::BAD_REQUEST, self.to_string()),
 
             // 401 Unauthorized
             AppError::InvalidCredentials
             | AppError::SessionExpired
+            | AppError::RefreshTokenReused
+            | AppError::InvalidAccessToken
             | AppError::NotAuthenticated => (StatusCode::UNAUTHORIZED, self.to_string()),
 
             // 402 Payment Required
@@ -200,20 +231,24 @@ impl IntoResponse for AppError {
             | AppError::OrderNotFound
             | AppError::EscrowNotFound
             | AppError::DisputeNotFound
-            | AppError::SlotNotFound => (StatusCode::NOT_FOUND, self.to_string()),
+            | AppError::SlotNotFound
+            | AppError::SellerApplicationNotFound => (StatusCode::NOT_FOUND, self.to_string()),
 
             // 409 Conflict
             AppError::UserAlreadyExists
             | AppError::ListingNotAvailable
             | AppError::OrderAlreadyCompleted
             | AppError::OrderCannotBeDisputed
-            | AppError::EscrowAlreadyReleased
-            | AppError::EscrowAlreadyRefunded
+            | AppError::OrderNotRatable
+            | AppError::OrderAlreadyRated
+            | AppError::InvalidEscrowTransition { .. }
             | AppError::DisputeAlreadyResolved
             | AppError::SlotNotAvailable
             | AppError::SlotOccupied
             | AppError::PriceLockExpired
             | AppError::CartEmpty
+            | AppError::SellerApplicationPending
+            | AppError::SellerApplicationAlreadyDecided
             | AppError::SellerInactive => (StatusCode::CONFLICT, self.to_string()),
 
             // 429 Too Many Requests
@@ -222,7 +257,8 @@ impl IntoResponse for AppError {
             // 500 Internal Server Error
             AppError::Database(_)
             | AppError::Internal(_)
-            | AppError::WithdrawalFailed(_) => {
+            | AppError::WithdrawalFailed(_)
+            | AppError::EscrowLedgerImbalance { .. } => {
                 tracing::error!("Internal error: {}", self);
                 (
                     StatusCode::INTERNAL_SERVER_ERROR,
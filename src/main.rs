@@ -1,3 +1,4 @@
+mod bloom;
 mod config;
 mod db;
 mod error;
@@ -6,6 +7,7 @@ mod models;
 mod routes;
 mod services;
 
+use std::net::SocketAddr;
 use std::sync::Arc;
 
 use axum::{
@@ -18,13 +20,22 @@ use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 use crate::config::Config;
 use crate::db::Database;
 use crate::middleware::{BrowsingFeeConfig, BrowsingFeeLayer};
-use crate::services::{CashuService, EscrowService, NostrService};
+use crate::models::DisputeResolution;
+use crate::services::{
+    CashuService, CheckoutService, ConnectorRouter, DepositIndexer, EscrowCoordinator,
+    EscrowEventBus, EscrowService, NostrService, NwcService, RateService, ReconciliationService,
+};
 
 /// Application state shared across all handlers
 pub struct AppState {
     pub db: Database,
-    pub cashu: CashuService,
+    pub cashu: ConnectorRouter,
     pub nostr: NostrService,
+    pub nwc: NwcService,
+    pub deposit_indexer: DepositIndexer,
+    pub rate: RateService,
+    pub escrow_coordinator: EscrowCoordinator,
+    pub escrow_events: EscrowEventBus,
     pub config: Config,
 }
 
@@ -48,24 +59,73 @@ async fn main() -> anyhow::Result<()> {
     db.run_migrations().await?;
     tracing::info!("Database initialized");
 
-    // Initialize Cashu wallet service
-    let cashu = CashuService::new(&config).await?;
+    // Initialize a Cashu wallet connector per configured mint (primary
+    // first, then fallbacks in priority order), and route payment
+    // operations across them
+    let mut mint_configs = vec![config.mint.clone()];
+    mint_configs.extend(config.additional_mints.iter().cloned());
+
+    let mut mints = Vec::new();
+    for (idx, mint_config) in mint_configs.iter().enumerate() {
+        let mut cfg = config.clone();
+        cfg.mint = mint_config.clone();
+        if idx > 0 {
+            cfg.mint.data_dir = format!("{}/mint-{}", config.mint.data_dir, idx);
+        }
+        mints.push(Arc::new(CashuService::new(&cfg, db.clone()).await?));
+    }
+
+    let cashu = ConnectorRouter::new(mints)?;
     let mint_info = cashu.mint_info();
     if cashu.is_mock_mode() {
         tracing::warn!("Running in MOCK payment mode - set mint.url in config for real payments");
     } else {
-        tracing::info!("Cashu wallet connected to mint: {}", mint_info.url);
+        tracing::info!(
+            "Cashu wallet connected to {} mint(s), primary: {}",
+            mint_configs.len(),
+            mint_info.url
+        );
     }
 
     // Initialize Nostr service
     let nostr = NostrService::new(&config)?;
     tracing::info!("Nostr service initialized");
 
+    // Initialize deposit indexer, seeding its bloom filter from any deposits
+    // left outstanding by a previous run
+    let deposit_indexer = DepositIndexer::new(&db).await?;
+    tracing::info!("Deposit indexer initialized");
+
+    // Initialize the Nostr Wallet Connect service, loading or generating its
+    // long-lived service keypair
+    let nwc = NwcService::new(&config)?;
+    tracing::info!("NWC service initialized, pubkey: {}", nwc.service_pubkey().to_hex());
+
+    // Initialize the BTC/fiat rate oracle for listings priced in fiat
+    let rate = RateService::new(&config);
+
+    // Initialize the escrow coordinator, loading or generating its
+    // long-lived arbiter keypair
+    let escrow_coordinator = EscrowCoordinator::new(&config)?;
+    tracing::info!(
+        "Escrow coordinator initialized, arbiter npub: {}",
+        escrow_coordinator.arbiter_npub()?
+    );
+
+    // Shared bus waking long-polling GET /orders/:id/events requests as
+    // soon as any escrow's status changes
+    let escrow_events = EscrowEventBus::new();
+
     // Create shared application state
     let state = Arc::new(AppState {
         db,
         cashu,
         nostr,
+        nwc,
+        deposit_indexer,
+        rate,
+        escrow_coordinator,
+        escrow_events,
         config: config.clone(),
     });
 
@@ -75,11 +135,44 @@ async fn main() -> anyhow::Result<()> {
         escrow_auto_release_task(bg_state).await;
     });
 
+    // Spawn background task for the deposit indexer
+    let indexer_state = state.clone();
+    tokio::spawn(async move {
+        deposit_indexer_task(indexer_state).await;
+    });
+
+    // Spawn background task to retry/reverse withdrawals stuck pending
+    let reconcile_state = state.clone();
+    tokio::spawn(async move {
+        withdrawal_reconcile_task(reconcile_state).await;
+    });
+
+    // Spawn background task to recover melt->mint bridge quotes stuck
+    // between the foreign melt and the home mint call
+    let bridge_state = state.clone();
+    tokio::spawn(async move {
+        bridge_mint_sweep_task(bridge_state).await;
+    });
+
+    // Spawn background task listening for Nostr Wallet Connect requests
+    let nwc_state = state.clone();
+    tokio::spawn(async move {
+        nwc_listen_task(nwc_state).await;
+    });
+
+    // Spawn background task publishing queued escrow coordination DMs
+    let escrow_dm_state = state.clone();
+    tokio::spawn(async move {
+        escrow_dm_relay_task(escrow_dm_state).await;
+    });
+
     // Configure browsing fee middleware
     let browsing_fee_config = BrowsingFeeConfig {
         min_fee_sats: config.browsing_fee_sats,
+        signing_key: config.session_secret.as_bytes().to_vec(),
         ..Default::default()
     };
+    let browsing_fee_layer = BrowsingFeeLayer::new(browsing_fee_config, state.clone()).await?;
 
     // Build router
     let app = Router::new()
@@ -89,51 +182,104 @@ async fn main() -> anyhow::Result<()> {
         // Auth routes
         .route("/login", get(routes::auth::login_page))
         .route("/login", post(routes::auth::login))
+        .route("/login/dev", post(routes::auth::login_dev))
         .route("/register", get(routes::auth::register_page))
         .route("/register", post(routes::auth::register))
         .route("/logout", post(routes::auth::logout))
+        .route("/auth/challenge", get(routes::auth::challenge))
+        .route("/auth/refresh", post(routes::auth::refresh))
+        .route("/api/auth/token", post(routes::auth::api_login))
+        .route("/api/auth/refresh", post(routes::auth::api_refresh))
         // Listing routes
         .route("/listings", get(routes::listings::index))
         .route("/listings/:id", get(routes::listings::show))
         .route("/listings/new", get(routes::listings::new_page))
         .route("/listings/new", post(routes::listings::create))
+        .route(
+            "/listings/seller/:npub/reputation",
+            get(routes::listings::seller_reputation),
+        )
+        // Account security routes
+        .route("/account/sessions", get(routes::account::sessions))
+        .route("/account/sessions/:id/revoke", post(routes::account::revoke))
+        .route(
+            "/account/sessions/revoke-others",
+            post(routes::account::revoke_others),
+        )
+        // Address book routes
+        .route("/addresses", get(routes::address::index))
+        .route("/addresses", post(routes::address::create))
+        .route("/addresses/:id/update", post(routes::address::update))
+        .route("/addresses/:id/delete", post(routes::address::delete))
         // Cart routes
         .route("/cart", get(routes::cart::show))
         .route("/cart/add/:listing_id", post(routes::cart::add))
         .route("/cart/remove/:item_id", post(routes::cart::remove))
         .route("/checkout", get(routes::cart::checkout_page))
         .route("/checkout", post(routes::cart::checkout))
+        .route(
+            "/checkout/items/:item_id/increment",
+            post(routes::cart::increment_item),
+        )
+        .route(
+            "/checkout/items/:item_id/decrement",
+            post(routes::cart::decrement_item),
+        )
         // Wallet routes
         .route("/wallet", get(routes::wallet::show))
         .route("/wallet/deposit", get(routes::wallet::deposit_page))
         .route("/wallet/deposit", post(routes::wallet::deposit))
         .route("/wallet/withdraw", get(routes::wallet::withdraw_page))
         .route("/wallet/withdraw", post(routes::wallet::withdraw))
+        .route("/wallet/export", post(routes::wallet::export))
+        .route("/wallet/nwc/connect", post(routes::wallet::nwc_connect))
         // Order routes
         .route("/orders", get(routes::orders::index))
         .route("/orders/:id", get(routes::orders::show))
         .route("/orders/:id/confirm", post(routes::orders::confirm))
         .route("/orders/:id/dispute", post(routes::orders::dispute))
+        .route("/orders/:id/events", get(routes::orders::events))
+        .route("/orders/:id/rate", post(routes::orders::rate))
         // Seller routes
         .route("/seller/dashboard", get(routes::seller::dashboard))
         .route("/seller/orders", get(routes::seller::orders))
         .route("/seller/orders/:id/ship", post(routes::seller::mark_shipped))
         .route("/seller/become", get(routes::seller::become_seller_page))
         .route("/seller/become", post(routes::seller::become_seller))
+        .route("/seller/apply", get(routes::seller::apply_page))
+        .route("/seller/apply", post(routes::seller::apply))
         .route("/seller/categories", get(routes::seller::categories_page))
         .route("/seller/categories", post(routes::seller::buy_category))
         // Admin routes
         .route("/admin", get(routes::admin::dashboard))
+        .route("/admin/stats", get(routes::admin::stats))
         .route("/admin/disputes", get(routes::admin::disputes))
         .route("/admin/disputes/:id", get(routes::admin::dispute_detail))
         .route(
             "/admin/disputes/:id/resolve",
             post(routes::admin::resolve_dispute),
         )
+        .route(
+            "/admin/users/:npub/revoke-sessions",
+            post(routes::admin::revoke_user_sessions),
+        )
+        .route("/admin/reconcile", get(routes::admin::reconcile))
+        .route(
+            "/admin/seller-applications",
+            get(routes::admin::seller_applications),
+        )
+        .route(
+            "/admin/seller-applications/:id/approve",
+            post(routes::admin::approve_seller_application),
+        )
+        .route(
+            "/admin/seller-applications/:id/deny",
+            post(routes::admin::deny_seller_application),
+        )
         // Static files
         .nest_service("/static", tower_http::services::ServeDir::new("static"))
         // Middleware
-        .layer(BrowsingFeeLayer::new(browsing_fee_config))
+        .layer(browsing_fee_layer)
         .layer(TraceLayer::new_for_http())
         .with_state(state);
 
@@ -142,19 +288,36 @@ async fn main() -> anyhow::Result<()> {
     let listener = tokio::net::TcpListener::bind(&addr).await?;
     tracing::info!("Server listening on {}", addr);
 
-    axum::serve(listener, app).await?;
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await?;
 
     Ok(())
 }
 
-/// Background task to process escrow auto-releases
+/// Background task to process escrow auto-releases, dispute timeouts, and
+/// checkout price-lock expiry
 async fn escrow_auto_release_task(state: Arc<AppState>) {
-    let mut interval = tokio::time::interval(std::time::Duration::from_secs(60));
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(
+        state.config.background_task_interval_secs,
+    ));
+
+    let default_resolution =
+        DisputeResolution::from_str(&state.config.dispute_timeout_resolution)
+            .unwrap_or(DisputeResolution::BuyerFull);
 
     loop {
         interval.tick().await;
 
-        match EscrowService::process_auto_releases(&state.db).await {
+        match EscrowService::process_auto_releases(
+            &state.db,
+            &state.escrow_coordinator,
+            &state.escrow_events,
+        )
+        .await
+        {
             Ok(count) => {
                 if count > 0 {
                     tracing::info!("Auto-released {} escrows", count);
@@ -164,5 +327,184 @@ async fn escrow_auto_release_task(state: Arc<AppState>) {
                 tracing::error!("Error processing auto-releases: {}", e);
             }
         }
+
+        match EscrowService::process_dispute_timeouts(
+            &state.db,
+            &state.escrow_coordinator,
+            &state.escrow_events,
+            default_resolution,
+        )
+        .await
+        {
+            Ok(count) => {
+                if count > 0 {
+                    tracing::info!("Auto-resolved {} timed-out disputes", count);
+                }
+            }
+            Err(e) => {
+                tracing::error!("Error processing dispute timeouts: {}", e);
+            }
+        }
+
+        match CheckoutService::expire_pending(&state.db).await {
+            Ok(count) => {
+                if count > 0 {
+                    tracing::info!("Expired {} stale checkout sessions", count);
+                }
+            }
+            Err(e) => {
+                tracing::error!("Error expiring checkout sessions: {}", e);
+            }
+        }
+    }
+}
+
+/// Background task to scan for and credit confirmed deposits
+async fn deposit_indexer_task(state: Arc<AppState>) {
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(30));
+
+    loop {
+        interval.tick().await;
+
+        match state.deposit_indexer.scan_once(&state.db, &state.cashu).await {
+            Ok(count) => {
+                if count > 0 {
+                    tracing::info!("Credited {} deposits", count);
+                }
+            }
+            Err(e) => {
+                tracing::error!("Error scanning deposits: {}", e);
+            }
+        }
+    }
+}
+
+/// Background task to retry withdrawals still pending melt confirmation,
+/// reversing them back to the user if a renewed attempt also fails.
+async fn withdrawal_reconcile_task(state: Arc<AppState>) {
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(60));
+
+    loop {
+        interval.tick().await;
+
+        match ReconciliationService::retry_stuck_withdrawals(&state.db, &state.cashu).await {
+            Ok(count) => {
+                if count > 0 {
+                    tracing::info!("Resolved {} stuck withdrawals", count);
+                }
+            }
+            Err(e) => {
+                tracing::error!("Error reconciling withdrawals: {}", e);
+            }
+        }
+    }
+}
+
+/// Background task to recover melt->mint bridge quotes (see
+/// `CashuService::receive_foreign_token`) that crashed after the foreign
+/// melt completed but before the home mint call, so the invoice they paid
+/// doesn't sit credited-nowhere forever.
+async fn bridge_mint_sweep_task(state: Arc<AppState>) {
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(60));
+
+    loop {
+        interval.tick().await;
+
+        match state.cashu.sweep_pending_bridge_mints().await {
+            Ok(count) => {
+                if count > 0 {
+                    tracing::info!("Recovered {} stuck bridge mints", count);
+                }
+            }
+            Err(e) => {
+                tracing::error!("Error sweeping bridge mints: {}", e);
+            }
+        }
+    }
+}
+
+/// Background task that keeps a Nostr relay subscription open for incoming
+/// NIP-47 requests and answers each one against this marketplace's wallet.
+/// Reconnects with a backoff on any relay error rather than exiting, since
+/// this is the only way NWC clients reach the wallet.
+async fn nwc_listen_task(state: Arc<AppState>) {
+    use nostr_sdk::prelude::*;
+
+    loop {
+        let client = Client::default();
+        if let Err(e) = client.add_relay(&state.nwc.relay_url).await {
+            tracing::error!("Failed to add NWC relay {}: {}", state.nwc.relay_url, e);
+            tokio::time::sleep(std::time::Duration::from_secs(30)).await;
+            continue;
+        }
+        client.connect().await;
+
+        let filter = state.nwc.request_filter(Timestamp::now());
+        if let Err(e) = client.subscribe(vec![filter], None).await {
+            tracing::error!("Failed to subscribe to NWC requests: {}", e);
+            tokio::time::sleep(std::time::Duration::from_secs(30)).await;
+            continue;
+        }
+        tracing::info!("Listening for NWC requests on {}", state.nwc.relay_url);
+
+        let mut notifications = client.notifications();
+        while let Ok(notification) = notifications.recv().await {
+            if let RelayPoolNotification::Event { event, .. } = notification {
+                match state
+                    .nwc
+                    .handle_request(&state.db, &state.cashu, &state.deposit_indexer, &event)
+                    .await
+                {
+                    Ok(response_event) => {
+                        if let Err(e) = client.send_event(response_event).await {
+                            tracing::error!("Failed to publish NWC response: {}", e);
+                        }
+                    }
+                    Err(e) => {
+                        tracing::warn!("Failed to handle NWC request: {}", e);
+                    }
+                }
+            }
+        }
+
+        tracing::warn!("NWC relay connection dropped, reconnecting");
+        tokio::time::sleep(std::time::Duration::from_secs(10)).await;
+    }
+}
+
+/// Background task that publishes queued escrow coordination DMs (see
+/// [`crate::services::EscrowCoordinator::notify`]) to the configured relay.
+/// Runs as a simple poll loop rather than a persistent subscription, since
+/// the coordinator only ever sends from this outbox — it doesn't need a
+/// standing relay connection the way `nwc_listen_task` does.
+async fn escrow_dm_relay_task(state: Arc<AppState>) {
+    use nostr_sdk::prelude::*;
+
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(15));
+
+    loop {
+        interval.tick().await;
+
+        let client = Client::default();
+        if let Err(e) = client.add_relay(&state.escrow_coordinator.relay_url).await {
+            tracing::error!("Failed to add escrow DM relay {}: {}", state.escrow_coordinator.relay_url, e);
+            continue;
+        }
+        client.connect().await;
+
+        match state
+            .escrow_coordinator
+            .publish_pending(&state.db, &client, 50)
+            .await
+        {
+            Ok(count) => {
+                if count > 0 {
+                    tracing::info!("Published {} escrow coordination DMs", count);
+                }
+            }
+            Err(e) => {
+                tracing::error!("Error publishing escrow coordination DMs: {}", e);
+            }
+        }
     }
 }
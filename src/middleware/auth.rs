@@ -6,12 +6,13 @@ use std::sync::Arc;
 use axum::{
     async_trait,
     extract::FromRequestParts,
-    http::{request::Parts, StatusCode},
+    http::{header, request::Parts, StatusCode},
     response::{IntoResponse, Redirect, Response},
 };
 use axum_extra::extract::CookieJar;
 
 use crate::models::User;
+use crate::services::JwtService;
 use crate::AppState;
 
 const SESSION_COOKIE: &str = "session";
@@ -42,11 +43,7 @@ impl FromRequestParts<Arc<AppState>> for CurrentUser {
         parts: &mut Parts,
         state: &Arc<AppState>,
     ) -> Result<Self, Self::Rejection> {
-        let jar = CookieJar::from_request_parts(parts, state)
-            .await
-            .map_err(|_| AuthError::Internal)?;
-
-        let user = get_user_from_session(state, &jar)
+        let user = get_user_from_bearer_or_session(parts, state)
             .await
             .map_err(|_| AuthError::Internal)?
             .ok_or(AuthError::NotAuthenticated)?;
@@ -63,11 +60,7 @@ impl FromRequestParts<Arc<AppState>> for OptionalUser {
         parts: &mut Parts,
         state: &Arc<AppState>,
     ) -> Result<Self, Self::Rejection> {
-        let jar = CookieJar::from_request_parts(parts, state)
-            .await
-            .map_err(|_| AuthError::Internal)?;
-
-        let user = get_user_from_session(state, &jar)
+        let user = get_user_from_bearer_or_session(parts, state)
             .await
             .map_err(|_| AuthError::Internal)?;
 
@@ -145,6 +138,52 @@ impl IntoResponse for AuthError {
     }
 }
 
+/// Resolve the current user from either a `Bearer` JWT in the
+/// `Authorization` header or, failing that, the session cookie — letting
+/// API clients authenticate without a browser session while leaving the
+/// cookie flow untouched.
+async fn get_user_from_bearer_or_session(
+    parts: &mut Parts,
+    state: &Arc<AppState>,
+) -> Result<Option<User>, sqlx::Error> {
+    if let Some(user) = get_user_from_bearer(state, parts).await? {
+        return Ok(Some(user));
+    }
+
+    let jar = CookieJar::from_request_parts(parts, state)
+        .await
+        .unwrap_or_default();
+
+    get_user_from_session(state, &jar).await
+}
+
+/// Verify a `Bearer` JWT access token against the `Authorization` header
+/// and load the user it names, if any.
+async fn get_user_from_bearer(
+    state: &AppState,
+    parts: &Parts,
+) -> Result<Option<User>, sqlx::Error> {
+    let token = match parts
+        .headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+    {
+        Some(token) => token,
+        None => return Ok(None),
+    };
+
+    let claims = match JwtService::verify(state.config.session_secret.as_bytes(), token) {
+        Ok(claims) => claims,
+        Err(_) => return Ok(None),
+    };
+
+    sqlx::query_as("SELECT * FROM users WHERE npub = ?")
+        .bind(&claims.sub)
+        .fetch_optional(state.db.pool())
+        .await
+}
+
 /// Get user from session cookie
 async fn get_user_from_session(
     state: &AppState,
@@ -179,6 +218,11 @@ async fn get_user_from_session(
             .bind(&session.user_npub)
             .execute(state.db.pool())
             .await?;
+
+        sqlx::query("UPDATE sessions SET last_seen_at = CURRENT_TIMESTAMP WHERE id = ?")
+            .bind(&session.id)
+            .execute(state.db.pool())
+            .await?;
     }
 
     Ok(user)
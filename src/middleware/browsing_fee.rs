@@ -1,3 +1,4 @@
+use std::sync::Arc;
 use std::task::{Context, Poll};
 
 use axum::{
@@ -5,13 +6,37 @@ use axum::{
     http::{header, Request, Response, StatusCode},
     response::IntoResponse,
 };
+use axum_extra::extract::cookie::{Cookie, SameSite};
+use chrono::{Duration, Utc};
 use tower::{Layer, Service};
 
+use crate::bloom::BloomFilter;
+use crate::error::AppResult;
+use crate::models::TransactionType;
+use crate::services::{AccessPassService, LedgerService, ACCOUNT_MINT_FLOAT};
+use crate::AppState;
+
+/// Bit width of the bloom filter guarding `spent_browsing_tokens` lookups.
+const SPENT_TOKEN_BLOOM_BITS: usize = 1 << 16;
+const SPENT_TOKEN_BLOOM_HASHES: u32 = 4;
+
+/// Cookie carrying a signed, time-boxed access pass minted on a successful
+/// browsing-fee redemption (see [`AccessPassService`]).
+const ACCESS_PASS_COOKIE: &str = "access_pass";
+
 /// Configuration for browsing fee middleware
 #[derive(Clone)]
 pub struct BrowsingFeeConfig {
     /// Minimum fee in sats
     pub min_fee_sats: u64,
+    /// Minutes of access one sat buys when redeeming a browsing fee token.
+    /// An admission pass covering `N` minutes is minted from `amount_sats *
+    /// minutes_per_sat`, so raising this gives browsers more time per sat.
+    pub minutes_per_sat: u64,
+    /// Key used to HMAC-sign issued access passes. Must be set from a
+    /// server secret (see `Config::session_secret`) before the layer is
+    /// built — a default/empty key would let anyone forge a pass.
+    pub signing_key: Vec<u8>,
     /// Paths that require browsing fee (prefix matching)
     pub protected_paths: Vec<String>,
     /// Paths that are always free
@@ -22,6 +47,8 @@ impl Default for BrowsingFeeConfig {
     fn default() -> Self {
         Self {
             min_fee_sats: 100,
+            minutes_per_sat: 1,
+            signing_key: Vec::new(),
             protected_paths: vec![
                 "/listings".to_string(),
             ],
@@ -43,11 +70,29 @@ impl Default for BrowsingFeeConfig {
 #[derive(Clone)]
 pub struct BrowsingFeeLayer {
     config: BrowsingFeeConfig,
+    state: Arc<AppState>,
+    spent_filter: Arc<tokio::sync::RwLock<BloomFilter>>,
 }
 
 impl BrowsingFeeLayer {
-    pub fn new(config: BrowsingFeeConfig) -> Self {
-        Self { config }
+    /// Build the layer, seeding its bloom filter from every spent browsing
+    /// token already on record so a restart doesn't reopen a window where a
+    /// replayed token would bypass the fast-path check.
+    pub async fn new(config: BrowsingFeeConfig, state: Arc<AppState>) -> anyhow::Result<Self> {
+        let mut filter = BloomFilter::new(SPENT_TOKEN_BLOOM_BITS, SPENT_TOKEN_BLOOM_HASHES);
+
+        let spent: Vec<(String,)> = sqlx::query_as("SELECT token_hash FROM spent_browsing_tokens")
+            .fetch_all(state.db.pool())
+            .await?;
+        for (hash,) in spent {
+            filter.insert(&hash);
+        }
+
+        Ok(Self {
+            config,
+            state,
+            spent_filter: Arc::new(tokio::sync::RwLock::new(filter)),
+        })
     }
 }
 
@@ -58,6 +103,8 @@ impl<S> Layer<S> for BrowsingFeeLayer {
         BrowsingFeeMiddleware {
             inner,
             config: self.config.clone(),
+            state: self.state.clone(),
+            spent_filter: self.spent_filter.clone(),
         }
     }
 }
@@ -67,6 +114,8 @@ impl<S> Layer<S> for BrowsingFeeLayer {
 pub struct BrowsingFeeMiddleware<S> {
     inner: S,
     config: BrowsingFeeConfig,
+    state: Arc<AppState>,
+    spent_filter: Arc<tokio::sync::RwLock<BloomFilter>>,
 }
 
 impl<S> Service<Request<Body>> for BrowsingFeeMiddleware<S>
@@ -87,6 +136,8 @@ where
     fn call(&mut self, req: Request<Body>) -> Self::Future {
         let path = req.uri().path().to_string();
         let config = self.config.clone();
+        let state = self.state.clone();
+        let spent_filter = self.spent_filter.clone();
         let mut inner = self.inner.clone();
 
         Box::pin(async move {
@@ -124,33 +175,173 @@ where
                 return inner.call(req).await;
             }
 
+            // An unexpired, correctly-signed access pass means the browsing
+            // fee was already paid this session - let the request through
+            // without redeeming another token.
+            let existing_pass = cookie_value(&req, ACCESS_PASS_COOKIE);
+            if let Some(pass) = &existing_pass {
+                if AccessPassService::verify(&config.signing_key, pass).is_some() {
+                    return inner.call(req).await;
+                }
+            }
+
             // If no token and not logged in, return 402 Payment Required
-            if token.is_none() {
-                let response = PaymentRequiredResponse {
+            let Some(token) = token else {
+                return Ok(PaymentRequiredResponse {
                     min_fee_sats: config.min_fee_sats,
-                    message: "Browsing fee required. Send X-Cashu header with valid token.".to_string(),
-                };
-                return Ok(response.into_response());
-            }
+                    message: "Browsing fee required. Send X-Cashu header with valid token."
+                        .to_string(),
+                }
+                .into_response());
+            };
 
-            // Token validation would happen here via AppState
-            // For now, we just check the token format
-            let token = token.unwrap();
             if !token.starts_with("cashuA") {
-                let response = PaymentRequiredResponse {
+                return Ok(PaymentRequiredResponse {
                     min_fee_sats: config.min_fee_sats,
                     message: "Invalid Cashu token format".to_string(),
-                };
-                return Ok(response.into_response());
+                }
+                .into_response());
+            }
+
+            let Ok(token_hash) = state.cashu.primary_mint().token_hash(&token) else {
+                return Ok(PaymentRequiredResponse {
+                    min_fee_sats: config.min_fee_sats,
+                    message: "Invalid Cashu token".to_string(),
+                }
+                .into_response());
+            };
+
+            // Bloom filter: a negative result means the hash is definitely
+            // not spent, so only a positive needs the authoritative DB hit.
+            let maybe_spent = spent_filter.read().await.might_contain(&token_hash);
+            if maybe_spent {
+                let already_spent: Option<(String,)> = sqlx::query_as(
+                    "SELECT token_hash FROM spent_browsing_tokens WHERE token_hash = ?",
+                )
+                .bind(&token_hash)
+                .fetch_optional(state.db.pool())
+                .await
+                .unwrap_or(None);
+
+                if already_spent.is_some() {
+                    return Ok(PaymentRequiredResponse {
+                        min_fee_sats: config.min_fee_sats,
+                        message: "Token already spent".to_string(),
+                    }
+                    .into_response());
+                }
             }
 
-            // Token looks valid, proceed
-            // In production, we'd validate via CashuService here
-            inner.call(req).await
+            // Redeem the token through the mint and verify it covers the fee
+            let amount = match state.cashu.receive_token(&token, None).await {
+                Ok(amount) if amount >= config.min_fee_sats => amount,
+                _ => {
+                    return Ok(PaymentRequiredResponse {
+                        min_fee_sats: config.min_fee_sats,
+                        message: "Token invalid or below minimum browsing fee".to_string(),
+                    }
+                    .into_response());
+                }
+            };
+
+            spent_filter.write().await.insert(&token_hash);
+
+            // Mark the token spent and credit the platform admin wallet in
+            // one transaction, through `LedgerService::post` rather than a
+            // raw read-then-write: the mint has already redeemed the token
+            // for good by this point, so a lost update on concurrent
+            // redemptions (or a failure that's silently swallowed) would
+            // leave a token marked spent with no matching credit anywhere.
+            let credited: AppResult<()> = async {
+                let mut db_tx = state.db.pool().begin().await?;
+
+                sqlx::query(
+                    "INSERT OR IGNORE INTO spent_browsing_tokens (token_hash, amount_sats, spent_at) VALUES (?, ?, CURRENT_TIMESTAMP)",
+                )
+                .bind(&token_hash)
+                .bind(amount as i64)
+                .execute(&mut *db_tx)
+                .await?;
+
+                LedgerService::post(
+                    &mut db_tx,
+                    ACCOUNT_MINT_FLOAT,
+                    &state.config.admin_npub,
+                    amount as i64,
+                    &String::from(TransactionType::Fee),
+                    Some(&token_hash),
+                )
+                .await?;
+
+                let (new_balance,): (i64,) =
+                    sqlx::query_as("SELECT wallet_balance FROM users WHERE npub = ?")
+                        .bind(&state.config.admin_npub)
+                        .fetch_one(&mut *db_tx)
+                        .await?;
+
+                sqlx::query(
+                    "INSERT INTO wallet_transactions (id, user_npub, transaction_type, amount, balance_after, reference_id, description, created_at) VALUES (?, ?, ?, ?, ?, ?, 'Browsing fee collected', CURRENT_TIMESTAMP)",
+                )
+                .bind(uuid::Uuid::new_v4().to_string())
+                .bind(&state.config.admin_npub)
+                .bind(String::from(TransactionType::Fee))
+                .bind(amount as i64)
+                .bind(new_balance)
+                .bind(&token_hash)
+                .execute(&mut *db_tx)
+                .await?;
+
+                db_tx.commit().await?;
+                Ok(())
+            }
+            .await;
+
+            if let Err(e) = credited {
+                tracing::error!(
+                    "failed to record browsing fee credit for token {}: {}",
+                    token_hash,
+                    e
+                );
+                return Ok(Response::builder()
+                    .status(StatusCode::INTERNAL_SERVER_ERROR)
+                    .body(Body::from("Failed to process browsing fee"))
+                    .expect("static response is valid")
+                    .into_response());
+            }
+
+            // Mint an access pass covering the time this fee bought, so the
+            // browser isn't charged again on every subsequent request.
+            let minutes = (amount * config.minutes_per_sat).max(1);
+            let expires_at = Utc::now() + Duration::minutes(minutes as i64);
+            let pass = AccessPassService::issue(&config.signing_key, expires_at);
+
+            let mut response = inner.call(req).await?;
+            if let Ok(header_value) = access_pass_cookie(pass).to_string().parse() {
+                response.headers_mut().append(header::SET_COOKIE, header_value);
+            }
+            Ok(response)
         })
     }
 }
 
+/// Extract a cookie's value from the raw `Cookie` request header.
+fn cookie_value(req: &Request<Body>, name: &str) -> Option<String> {
+    let header = req.headers().get(header::COOKIE)?.to_str().ok()?;
+    header.split(';').map(str::trim).find_map(|pair| {
+        let (key, value) = pair.split_once('=')?;
+        (key == name).then(|| value.to_string())
+    })
+}
+
+fn access_pass_cookie(value: String) -> Cookie<'static> {
+    Cookie::build((ACCESS_PASS_COOKIE, value))
+        .path("/")
+        .http_only(true)
+        .secure(true)
+        .same_site(SameSite::Strict)
+        .build()
+}
+
 /// Response for 402 Payment Required
 struct PaymentRequiredResponse {
     min_fee_sats: u64,
@@ -172,9 +363,3 @@ impl IntoResponse for PaymentRequiredResponse {
             .unwrap()
     }
 }
-
-/// Helper to create browsing fee middleware with state access
-#[allow(dead_code)]
-pub fn browsing_fee_layer(config: BrowsingFeeConfig) -> BrowsingFeeLayer {
-    BrowsingFeeLayer::new(config)
-}
@@ -0,0 +1,39 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+/// A buyer's saved shipping address, reusable across checkouts
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct Address {
+    pub id: String,
+    pub user_npub: String,
+    pub name: String,
+    pub street: String,
+    pub city: String,
+    pub country: String,
+    pub zip: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Snapshot of the address an order shipped to, taken at checkout time so
+/// later edits (or deletion) of the buyer's address book don't change the
+/// historical record a seller already fulfilled against.
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct OrderAddress {
+    pub order_id: String,
+    pub name: String,
+    pub street: String,
+    pub city: String,
+    pub country: String,
+    pub zip: String,
+}
+
+/// Create/update address request
+#[derive(Debug, Clone, Deserialize)]
+pub struct AddressRequest {
+    pub name: String,
+    pub street: String,
+    pub city: String,
+    pub country: String,
+    pub zip: String,
+}
@@ -2,7 +2,16 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 
+use crate::error::{AppError, AppResult};
+
 /// Escrow model
+///
+/// When `locked_proofs` is set, the escrowed amount is not a bookkeeping
+/// entry but an actual Cashu token whose proofs carry a NUT-11 P2PK spending
+/// condition: a 2-of-3 multisig across `buyer_pubkey`/`seller_pubkey`/
+/// `arbiter_pubkey` with `auto_release_at` as the NUT-11 `locktime` and the
+/// seller as the refund key, so the seller can unilaterally claim once the
+/// locktime has passed.
 #[derive(Debug, Clone, Serialize, FromRow)]
 pub struct Escrow {
     pub id: String,
@@ -13,6 +22,14 @@ pub struct Escrow {
     pub auto_release_at: DateTime<Utc>,
     pub created_at: DateTime<Utc>,
     pub resolved_at: Option<DateTime<Utc>>,
+    /// Marketplace arbiter npub that co-signs the 2-of-3 P2PK condition
+    pub arbiter_npub: Option<String>,
+    /// Serialized Cashu token (cashuA...) holding the P2PK-locked proofs
+    pub locked_proofs: Option<String>,
+    /// Serialized [`EscrowPlan`] (JSON) governing how this escrow resolves;
+    /// collapses as witnesses (buyer confirmation, timelock, admin
+    /// arbitration) are applied via [`EscrowPlan::apply_witness`]
+    pub plan: Option<String>,
 }
 
 /// Escrow status
@@ -48,6 +65,39 @@ impl From<EscrowStatus> for String {
     }
 }
 
+impl EscrowStatus {
+    /// Legal next states from `self`. `Released`/`Refunded` are terminal —
+    /// no outgoing edges, matching that a settled escrow can never move
+    /// again.
+    fn legal_next_states(self) -> &'static [EscrowStatus] {
+        match self {
+            EscrowStatus::Held => &[
+                EscrowStatus::Released,
+                EscrowStatus::Disputed,
+                EscrowStatus::Refunded,
+            ],
+            EscrowStatus::Disputed => &[EscrowStatus::Released, EscrowStatus::Refunded],
+            EscrowStatus::Released | EscrowStatus::Refunded => &[],
+        }
+    }
+
+    /// Assert that `from -> to` is a legal edge in the escrow state
+    /// machine, so callers get a precise
+    /// [`AppError::InvalidEscrowTransition`] instead of reusing an
+    /// unrelated error (or a SQL `WHERE status = ...` guard) to mean "this
+    /// transition isn't allowed right now".
+    pub fn assert_transition(from: EscrowStatus, to: EscrowStatus) -> AppResult<()> {
+        if from.legal_next_states().contains(&to) {
+            Ok(())
+        } else {
+            Err(AppError::InvalidEscrowTransition {
+                from: from.into(),
+                to: to.into(),
+            })
+        }
+    }
+}
+
 impl Escrow {
     /// Get status as enum
     pub fn status_enum(&self) -> EscrowStatus {
@@ -55,20 +105,38 @@ impl Escrow {
     }
 
     /// Check if escrow can be released
+    ///
+    /// For P2PK-locked escrows this means the buyer (or the arbiter) can
+    /// co-sign a swap to the seller's key; the server never holds a key
+    /// capable of releasing funds on its own.
     pub fn can_release(&self) -> bool {
         self.status_enum() == EscrowStatus::Held
     }
 
     /// Check if escrow can be refunded
+    ///
+    /// For P2PK-locked escrows this means the seller + arbiter co-sign a
+    /// swap back to the buyer's key.
     pub fn can_refund(&self) -> bool {
         self.status_enum() == EscrowStatus::Held
     }
 
     /// Check if escrow should auto-release
+    ///
+    /// Once `auto_release_at` (the NUT-11 `locktime` on the locked proofs)
+    /// has passed, the refund pubkey on the spending condition becomes
+    /// valid and the seller can unilaterally claim without buyer/arbiter
+    /// signatures.
     pub fn should_auto_release(&self) -> bool {
         self.status_enum() == EscrowStatus::Held && self.auto_release_at <= Utc::now()
     }
 
+    /// Whether this escrow is backed by a real P2PK-locked Cashu token
+    /// rather than a plain database-balance hold
+    pub fn is_p2pk_locked(&self) -> bool {
+        self.locked_proofs.is_some()
+    }
+
     /// Get time until auto-release in seconds
     pub fn time_until_release(&self) -> i64 {
         (self.auto_release_at - Utc::now()).num_seconds().max(0)
@@ -200,6 +268,213 @@ impl DisputeResolution {
             }
         }
     }
+
+    /// Break a resolution down into the concrete set of P2PK swaps/
+    /// signatures needed to carry it out against a 2-of-3
+    /// buyer/seller/arbiter locked token.
+    ///
+    /// `BuyerFull` needs the arbiter and buyer to co-sign a swap to the
+    /// buyer's key; `SellerFull` needs the arbiter and seller to co-sign a
+    /// swap to the seller's key; `Split` produces two independent swaps in
+    /// the computed proportions, each requiring the same two signers as
+    /// the corresponding full-release case; `Burn` produces a swap to a
+    /// key nobody holds (the escrow's own id, which is not a valid nsec),
+    /// rendering the proofs permanently unspendable.
+    pub fn calculate_release_plan(&self, total: i64, escrow_id: &str) -> Vec<P2pkSwap> {
+        match self {
+            Self::BuyerFull => vec![P2pkSwap {
+                amount: total,
+                destination: SwapDestination::Buyer,
+                co_signers: CoSigners::ArbiterAndBuyer,
+            }],
+            Self::SellerFull => vec![P2pkSwap {
+                amount: total,
+                destination: SwapDestination::Seller,
+                co_signers: CoSigners::ArbiterAndSeller,
+            }],
+            Self::Split { .. } => {
+                let (buyer_amount, seller_amount) = self.calculate_amounts(total);
+                let mut swaps = Vec::new();
+                if buyer_amount > 0 {
+                    swaps.push(P2pkSwap {
+                        amount: buyer_amount,
+                        destination: SwapDestination::Buyer,
+                        co_signers: CoSigners::ArbiterAndBuyer,
+                    });
+                }
+                if seller_amount > 0 {
+                    swaps.push(P2pkSwap {
+                        amount: seller_amount,
+                        destination: SwapDestination::Seller,
+                        co_signers: CoSigners::ArbiterAndSeller,
+                    });
+                }
+                swaps
+            }
+            Self::Burn => vec![P2pkSwap {
+                amount: total,
+                destination: SwapDestination::Unspendable(escrow_id.to_string()),
+                co_signers: CoSigners::ArbiterAndSeller,
+            }],
+        }
+    }
+}
+
+/// One P2PK swap required to carry out a dispute resolution against a
+/// locked escrow token
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct P2pkSwap {
+    pub amount: i64,
+    pub destination: SwapDestination,
+    pub co_signers: CoSigners,
+}
+
+/// Who receives a swap's output proofs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum SwapDestination {
+    Buyer,
+    Seller,
+    /// Locked to an unspendable key derived from the escrow id (burn)
+    Unspendable(String),
+}
+
+/// Which two of the buyer/seller/arbiter set must co-sign a swap
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CoSigners {
+    ArbiterAndBuyer,
+    ArbiterAndSeller,
+}
+
+/// A witness fed into an [`EscrowPlan`] to satisfy one of its conditions
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Witness {
+    /// `npub` signed an approval (buyer confirmation or admin arbitration)
+    Signed(String),
+    /// The server clock has reached this instant (timelock sweep)
+    Now(DateTime<Utc>),
+}
+
+/// A condition gating an [`EscrowPlan::And`] branch
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Condition {
+    /// Satisfied when `npub` provides a [`Witness::Signed`] for itself
+    Signature(String),
+}
+
+/// Who a [`EscrowPlan::Payment`] leaf pays out to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Payee {
+    Buyer,
+    Seller,
+}
+
+/// Conditional escrow payment plan: a small expression tree in the style
+/// of a payment-channel "budget contract", evaluated by feeding in
+/// [`Witness`]es one at a time via [`EscrowPlan::apply_witness`] until it
+/// collapses to a bare [`EscrowPlan::Payment`].
+///
+/// A typical purchase plan is `Or(And(Signature(buyer) -> pay seller),
+/// Or(After(ship_time + grace -> pay seller), Signature(admin) -> refund
+/// buyer))`: the buyer can confirm receipt at any time, the seller is
+/// paid automatically once the grace period elapses, or an admin can
+/// step in and refund the buyer.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum EscrowPlan {
+    /// A terminal payout of `amount` to `payee`
+    Payment { amount: i64, payee: Payee },
+    /// Satisfied by whichever branch reduces to a `Payment` first
+    Or(Box<EscrowPlan>, Box<EscrowPlan>),
+    /// `inner` only reduces once `condition` is satisfied by the witness
+    And(Condition, Box<EscrowPlan>),
+    /// `inner` only reduces once the server clock passes `timestamp`
+    After(DateTime<Utc>, Box<EscrowPlan>),
+}
+
+impl EscrowPlan {
+    /// The standard purchase plan: buyer confirmation or the auto-release
+    /// timelock pays the seller; admin arbitration refunds the buyer.
+    pub fn purchase_plan(
+        amount: i64,
+        buyer_npub: &str,
+        admin_npub: &str,
+        auto_release_at: DateTime<Utc>,
+    ) -> Self {
+        Self::Or(
+            Box::new(Self::And(
+                Condition::Signature(buyer_npub.to_string()),
+                Box::new(Self::Payment {
+                    amount,
+                    payee: Payee::Seller,
+                }),
+            )),
+            Box::new(Self::Or(
+                Box::new(Self::After(
+                    auto_release_at,
+                    Box::new(Self::Payment {
+                        amount,
+                        payee: Payee::Seller,
+                    }),
+                )),
+                Box::new(Self::And(
+                    Condition::Signature(admin_npub.to_string()),
+                    Box::new(Self::Payment {
+                        amount,
+                        payee: Payee::Buyer,
+                    }),
+                )),
+            )),
+        )
+    }
+
+    /// If this plan has fully reduced to a terminal payout, return it
+    pub fn as_payment(&self) -> Option<(i64, Payee)> {
+        match self {
+            Self::Payment { amount, payee } => Some((*amount, *payee)),
+            _ => None,
+        }
+    }
+
+    /// Collapse whichever branches the witness satisfies, returning the
+    /// reduced plan. Applying the same witness repeatedly is a no-op once
+    /// nothing more can reduce; callers should keep applying new witnesses
+    /// (buyer confirmation, timelock sweeps, admin arbitration) until
+    /// [`Self::as_payment`] returns `Some`.
+    pub fn apply_witness(self, witness: &Witness) -> Self {
+        match self {
+            Self::Payment { .. } => self,
+            Self::Or(a, b) => {
+                let a = a.apply_witness(witness);
+                if a.as_payment().is_some() {
+                    return a;
+                }
+                let b = b.apply_witness(witness);
+                if b.as_payment().is_some() {
+                    return b;
+                }
+                Self::Or(Box::new(a), Box::new(b))
+            }
+            Self::And(condition, inner) => {
+                if condition.is_satisfied_by(witness) {
+                    inner.apply_witness(witness)
+                } else {
+                    Self::And(condition, inner)
+                }
+            }
+            Self::After(timestamp, inner) => match witness {
+                Witness::Now(now) if *now >= timestamp => inner.apply_witness(witness),
+                _ => Self::After(timestamp, inner),
+            },
+        }
+    }
+}
+
+impl Condition {
+    fn is_satisfied_by(&self, witness: &Witness) -> bool {
+        match (self, witness) {
+            (Condition::Signature(npub), Witness::Signed(signer)) => npub == signer,
+            _ => false,
+        }
+    }
 }
 
 /// Dispute evidence
@@ -248,3 +523,60 @@ pub struct SubmitEvidenceRequest {
     pub evidence_type: String,
     pub content: String,
 }
+
+/// One queued or delivered coordination DM in an escrow's P2PK handshake
+/// (see [`crate::services::EscrowCoordinator`]). `content` is the NIP-44
+/// ciphertext, not plaintext — this row is an outbox entry, not a readable
+/// transcript.
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct EscrowMessage {
+    pub id: String,
+    pub escrow_id: String,
+    pub recipient_npub: String,
+    pub kind: String,
+    pub content: String,
+    pub sent_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// What a coordination DM is telling its recipient
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EscrowMessageKind {
+    /// Sent to the seller when the buyer's payment locks a P2PK token:
+    /// carries the locked token and the 2-of-3 condition it must satisfy.
+    Lock,
+    /// Sent to the seller once a release witness is available: lets them
+    /// assemble their half of the 2-of-3 signature alongside the arbiter's.
+    ReleaseWitness,
+    /// Sent to the buyer once a refund witness is available.
+    RefundWitness,
+    /// Sent to both parties when a dispute opens, pausing the handshake
+    /// until an admin arbitrates.
+    DisputeOpened,
+}
+
+impl From<EscrowMessageKind> for String {
+    fn from(kind: EscrowMessageKind) -> Self {
+        match kind {
+            EscrowMessageKind::Lock => "lock",
+            EscrowMessageKind::ReleaseWitness => "release_witness",
+            EscrowMessageKind::RefundWitness => "refund_witness",
+            EscrowMessageKind::DisputeOpened => "dispute_opened",
+        }
+        .to_string()
+    }
+}
+
+/// One escrow status transition, logged so a client can watch an order's
+/// lifecycle (held -> released/disputed -> refunded/released) without
+/// polling the orders page. `id` is a strictly increasing row id, used as
+/// the long-poll cursor by [`crate::services::EscrowEventService`].
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct EscrowEvent {
+    pub id: i64,
+    pub escrow_id: String,
+    pub old_status: String,
+    pub new_status: String,
+    pub amount: i64,
+    pub created_at: DateTime<Utc>,
+}
@@ -1,6 +1,8 @@
 use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
+use std::str::FromStr;
 
 use super::SellerCategory;
 
@@ -18,6 +20,16 @@ pub struct Listing {
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub expires_at: DateTime<Utc>,
+    /// Currency the seller priced this listing in (e.g. "usd"), if they
+    /// chose fiat pricing over setting `price` directly. `price` holds the
+    /// sats figure converted through [`crate::services::RateService`] when
+    /// the listing is created; checkout re-converts the fiat price at lock
+    /// time instead of reading `price`, and does not update it.
+    pub fiat_currency: Option<String>,
+    /// Decimal string (parse with [`Self::fiat_price_decimal`]) — stored as
+    /// text since the rest of the schema has no arbitrary-precision column
+    /// type.
+    pub fiat_price: Option<String>,
 }
 
 impl Listing {
@@ -37,6 +49,11 @@ impl Listing {
     pub fn is_expired(&self) -> bool {
         self.expires_at <= Utc::now()
     }
+
+    /// Parsed fiat price, if this listing was priced in fiat
+    pub fn fiat_price_decimal(&self) -> Option<Decimal> {
+        self.fiat_price.as_deref().and_then(|p| Decimal::from_str(p).ok())
+    }
 }
 
 /// Listing image
@@ -57,6 +74,8 @@ pub struct CartItem {
     pub user_npub: String,
     pub listing_id: String,
     pub added_at: DateTime<Utc>,
+    pub quantity: i64,
+    pub quantity_unit: String,
 }
 
 /// Cart item with listing details (for display)
@@ -79,6 +98,10 @@ pub struct CheckoutSession {
     pub created_at: DateTime<Utc>,
     pub expires_at: DateTime<Utc>,
     pub paid_at: Option<DateTime<Utc>>,
+    /// Optional buyer-entered free text (gift message, delivery
+    /// preferences, etc.), copied onto each resulting order so it survives
+    /// the checkout session being cleared.
+    pub notes: Option<String>,
 }
 
 /// Checkout session status
@@ -118,8 +141,24 @@ pub struct CheckoutItem {
     pub checkout_id: String,
     pub listing_id: String,
     pub seller_npub: String,
+    /// Per-unit price, locked at checkout-page-load time. The line total is
+    /// `locked_price * quantity`.
     pub locked_price: i64,
     pub encrypted_shipping: Option<String>,
+    /// The exact fiat figure the buyer agreed to, for a listing priced in
+    /// fiat — fixed at checkout-lock time independent of later rate moves,
+    /// so a receipt always shows what the buyer actually signed up for.
+    pub locked_fiat_currency: Option<String>,
+    pub locked_fiat_amount: Option<String>,
+    pub quantity: i64,
+    pub quantity_unit: String,
+}
+
+impl CheckoutItem {
+    /// Total price for this line: per-unit `locked_price` times `quantity`.
+    pub fn line_total(&self) -> i64 {
+        self.locked_price * self.quantity
+    }
 }
 
 /// Featured slot configuration
@@ -162,6 +201,11 @@ pub struct CreateListingRequest {
     pub price: i64,
     pub category: String,
     pub stock: Option<i64>,
+    /// Optional fiat price, e.g. "19.99" — when set with `fiat_currency`,
+    /// overrides `price` by converting through the current rate instead of
+    /// using it directly.
+    pub fiat_price: Option<String>,
+    pub fiat_currency: Option<String>,
 }
 
 /// Search query for listings
@@ -1,12 +1,16 @@
 // Model types are part of the public API - some methods/structs may not be used internally yet
 #![allow(dead_code)]
 
+mod address;
 mod escrow;
 mod listing;
+mod nwc;
 mod order;
 mod user;
 
+pub use address::*;
 pub use escrow::*;
 pub use listing::*;
+pub use nwc::*;
 pub use order::*;
 pub use user::*;
@@ -0,0 +1,23 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::FromRow;
+
+/// A Nostr Wallet Connect (NIP-47) app connection: lets an external NWC
+/// client act on one user's wallet over Nostr. Only the app's public key is
+/// persisted — the paired secret key lives solely in the `nostr+
+/// walletconnect://` URI handed to the user once at creation time.
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct NwcConnection {
+    pub id: String,
+    pub user_npub: String,
+    pub app_pubkey: String,
+    pub label: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+impl NwcConnection {
+    pub fn is_revoked(&self) -> bool {
+        self.revoked_at.is_some()
+    }
+}
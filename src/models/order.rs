@@ -15,6 +15,10 @@ pub struct Order {
     pub shipped_at: Option<DateTime<Utc>>,
     pub completed_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
+    /// Buyer's free-text note from checkout (gift message, delivery
+    /// preferences, etc.), copied from the checkout session at order
+    /// creation so it's still available once that session is cleared.
+    pub notes: Option<String>,
 }
 
 /// Order status
@@ -87,9 +91,12 @@ pub struct OrderItem {
     pub id: String,
     pub order_id: String,
     pub listing_id: String,
+    /// Per-unit price. The line total is `price * quantity`.
     pub price: i64,
     pub encrypted_shipping: Option<String>,
     pub digital_content: Option<String>,
+    pub quantity: i64,
+    pub quantity_unit: String,
 }
 
 /// Order rating
@@ -151,4 +158,6 @@ pub struct SendMessageRequest {
 #[derive(Debug, Clone, Deserialize)]
 pub struct MarkShippedRequest {
     pub tracking_info: Option<String>,
+    /// NIP-98 auth event (JSON) proving the seller signed this exact request
+    pub auth_event: String,
 }
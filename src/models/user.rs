@@ -100,6 +100,69 @@ pub struct SellerCategoryAccess {
     pub paid_at: DateTime<Utc>,
 }
 
+/// Status of a [`SellerApplication`]. `Disabled` is reserved for an admin
+/// pulling a previously-approved seller's selling rights without touching
+/// their bonded categories directly; nothing sets it yet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SellerApplicationStatus {
+    Applying,
+    Approved,
+    Denied,
+    Disabled,
+}
+
+impl From<String> for SellerApplicationStatus {
+    fn from(s: String) -> Self {
+        match s.as_str() {
+            "approved" => SellerApplicationStatus::Approved,
+            "denied" => SellerApplicationStatus::Denied,
+            "disabled" => SellerApplicationStatus::Disabled,
+            _ => SellerApplicationStatus::Applying,
+        }
+    }
+}
+
+impl From<SellerApplicationStatus> for String {
+    fn from(status: SellerApplicationStatus) -> Self {
+        match status {
+            SellerApplicationStatus::Applying => "applying".to_string(),
+            SellerApplicationStatus::Approved => "approved".to_string(),
+            SellerApplicationStatus::Denied => "denied".to_string(),
+            SellerApplicationStatus::Disabled => "disabled".to_string(),
+        }
+    }
+}
+
+/// A buyer's application to become a seller, reviewed by an admin instead
+/// of being granted automatically. An approved application promotes the
+/// applicant's role and grants the categories they requested; a denied one
+/// keeps them a buyer and records why, visible back to the applicant on
+/// `GET /seller/apply`. Coexists with the instant, bond-payment-only path
+/// in `routes::seller::become_seller` rather than replacing it.
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct SellerApplication {
+    pub id: String,
+    pub user_npub: String,
+    /// JSON array of requested category ids (e.g. `["digital","services"]`).
+    pub requested_categories: String,
+    #[sqlx(try_from = "String")]
+    pub status: SellerApplicationStatus,
+    pub denial_reason: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub decided_at: Option<DateTime<Utc>>,
+}
+
+impl SellerApplication {
+    pub fn is_pending(&self) -> bool {
+        self.status == SellerApplicationStatus::Applying
+    }
+
+    pub fn requested_categories(&self) -> Vec<String> {
+        serde_json::from_str(&self.requested_categories).unwrap_or_default()
+    }
+}
+
 /// User session
 #[derive(Debug, Clone, Serialize, FromRow)]
 pub struct Session {
@@ -107,6 +170,15 @@ pub struct Session {
     pub user_npub: String,
     pub expires_at: DateTime<Utc>,
     pub created_at: DateTime<Utc>,
+    /// Last time this session was presented on an authenticated request —
+    /// distinct from `users.last_active_at`, which tracks the user across
+    /// all of their sessions, not just this one.
+    pub last_seen_at: Option<DateTime<Utc>>,
+    /// Coarse `User-Agent` string captured at login, so the account-security
+    /// session list has something to distinguish entries by beyond the id.
+    pub user_agent: Option<String>,
+    /// Client address captured at login.
+    pub ip_address: Option<String>,
 }
 
 impl Session {
@@ -116,6 +188,72 @@ impl Session {
     }
 }
 
+/// A one-time login challenge issued to a browser before it authenticates,
+/// so the signed-event flow in `routes::auth` has something to check the
+/// `challenge` tag against. Short-lived and deleted once consumed — see
+/// `routes::auth::login`.
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct LoginChallenge {
+    pub id: String,
+    pub challenge: String,
+    pub expires_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl LoginChallenge {
+    pub fn is_expired(&self) -> bool {
+        self.expires_at < Utc::now()
+    }
+}
+
+/// Refresh token record. The plaintext token is handed to the client and
+/// never stored — only its hash. `family_id` ties every token descended
+/// from the same login together, so a token that's already been rotated
+/// (`revoked_at` set) being presented again is a reuse/theft signal that
+/// revokes the whole family, not just that one token.
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct RefreshToken {
+    pub id: String,
+    pub user_npub: String,
+    pub token_hash: String,
+    pub family_id: String,
+    pub rotated_from: Option<String>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub expires_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl RefreshToken {
+    pub fn is_expired(&self) -> bool {
+        self.expires_at < Utc::now()
+    }
+
+    pub fn is_revoked(&self) -> bool {
+        self.revoked_at.is_some()
+    }
+}
+
+/// A long-lived API refresh token backing the JWT bearer-auth flow (see
+/// `services::JwtService` and `routes::auth::api_refresh`) for
+/// programmatic clients. Unlike `RefreshToken`, the jti itself is the
+/// bearer value presented back to rotate it rather than a hash, mirroring
+/// how `sessions.id` is already looked up directly — a revocable capability
+/// row, not a long-term credential warranting hash-at-rest.
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct ApiToken {
+    pub jti: String,
+    pub user_npub: String,
+    pub role: String,
+    pub issued_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl ApiToken {
+    pub fn is_expired(&self) -> bool {
+        self.expires_at < Utc::now()
+    }
+}
+
 /// Seller statistics
 #[derive(Debug, Clone, Serialize, FromRow)]
 pub struct SellerStats {
@@ -139,6 +277,13 @@ pub struct WalletTransaction {
     pub balance_after: i64,
     pub reference_id: Option<String>,
     pub description: Option<String>,
+    /// Caller-supplied tag (e.g. an order reference) for payout
+    /// reconciliation and receipts — see
+    /// [`crate::services::LedgerService::get_transactions_by_label`].
+    pub label: Option<String>,
+    pub listing_id: Option<String>,
+    pub checkout_id: Option<String>,
+    pub fee_sats: i64,
     pub created_at: DateTime<Utc>,
 }
 
@@ -154,6 +299,7 @@ pub enum TransactionType {
     EscrowHold,
     EscrowRelease,
     EscrowRefund,
+    EscrowBurn,
 }
 
 impl From<TransactionType> for String {
@@ -168,6 +314,7 @@ impl From<TransactionType> for String {
             TransactionType::EscrowHold => "escrow_hold",
             TransactionType::EscrowRelease => "escrow_release",
             TransactionType::EscrowRefund => "escrow_refund",
+            TransactionType::EscrowBurn => "escrow_burn",
         }
         .to_string()
     }
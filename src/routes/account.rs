@@ -0,0 +1,103 @@
+use std::sync::Arc;
+
+use askama::Template;
+use axum::{
+    extract::{Path, State},
+    response::{Html, Redirect},
+};
+use axum_extra::extract::CookieJar;
+
+use crate::error::{AppError, AppResult};
+use crate::models::Session;
+use crate::routes::auth::get_current_user;
+use crate::AppState;
+
+const SESSION_COOKIE: &str = "session";
+
+#[derive(Template)]
+#[template(path = "account/sessions.html")]
+struct SessionsTemplate {
+    title: String,
+    sessions: Vec<Session>,
+    current_session_id: String,
+}
+
+/// List the current user's active (non-expired) sessions, so they can spot
+/// and kill a leaked or stale one without rotating their nsec.
+pub async fn sessions(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+) -> AppResult<Html<String>> {
+    let user = get_current_user(&state, &jar)
+        .await?
+        .ok_or(AppError::NotAuthenticated)?;
+
+    let current_session_id = jar
+        .get(SESSION_COOKIE)
+        .map(|c| c.value().to_string())
+        .ok_or(AppError::NotAuthenticated)?;
+
+    let sessions: Vec<Session> = sqlx::query_as(
+        "SELECT * FROM sessions WHERE user_npub = ? AND expires_at > CURRENT_TIMESTAMP ORDER BY created_at DESC",
+    )
+    .bind(&user.npub)
+    .fetch_all(state.db.pool())
+    .await?;
+
+    let template = SessionsTemplate {
+        title: "Active Sessions".to_string(),
+        sessions,
+        current_session_id,
+    };
+
+    let html = template
+        .render()
+        .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    Ok(Html(html))
+}
+
+/// Revoke one of the current user's sessions by id. Scoped to the caller's
+/// own `user_npub` so one account can't be used to delete another's
+/// session by guessing its id.
+pub async fn revoke(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Path(session_id): Path<String>,
+) -> AppResult<Redirect> {
+    let user = get_current_user(&state, &jar)
+        .await?
+        .ok_or(AppError::NotAuthenticated)?;
+
+    sqlx::query("DELETE FROM sessions WHERE id = ? AND user_npub = ?")
+        .bind(&session_id)
+        .bind(&user.npub)
+        .execute(state.db.pool())
+        .await?;
+
+    Ok(Redirect::to("/account/sessions"))
+}
+
+/// Revoke every session belonging to the current user except the one
+/// presented in this request's own cookie.
+pub async fn revoke_others(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+) -> AppResult<Redirect> {
+    let user = get_current_user(&state, &jar)
+        .await?
+        .ok_or(AppError::NotAuthenticated)?;
+
+    let current_session_id = jar
+        .get(SESSION_COOKIE)
+        .map(|c| c.value().to_string())
+        .ok_or(AppError::NotAuthenticated)?;
+
+    sqlx::query("DELETE FROM sessions WHERE user_npub = ? AND id != ?")
+        .bind(&user.npub)
+        .bind(&current_session_id)
+        .execute(state.db.pool())
+        .await?;
+
+    Ok(Redirect::to("/account/sessions"))
+}
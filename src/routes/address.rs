@@ -0,0 +1,121 @@
+use std::sync::Arc;
+
+use askama::Template;
+use axum::{
+    extract::{Path, State},
+    response::{Html, Redirect},
+    Form,
+};
+use axum_extra::extract::CookieJar;
+
+use crate::error::{AppError, AppResult};
+use crate::models::{Address, AddressRequest};
+use crate::routes::auth::get_current_user;
+use crate::AppState;
+
+#[derive(Template)]
+#[template(path = "addresses/index.html")]
+struct AddressesIndexTemplate {
+    title: String,
+    addresses: Vec<Address>,
+}
+
+/// List the buyer's saved addresses
+pub async fn index(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+) -> AppResult<Html<String>> {
+    let user = get_current_user(&state, &jar)
+        .await?
+        .ok_or(AppError::NotAuthenticated)?;
+
+    let addresses: Vec<Address> =
+        sqlx::query_as("SELECT * FROM addresses WHERE user_npub = ? ORDER BY created_at DESC")
+            .bind(&user.npub)
+            .fetch_all(state.db.pool())
+            .await?;
+
+    let template = AddressesIndexTemplate {
+        title: "My Addresses".to_string(),
+        addresses,
+    };
+
+    let html = template
+        .render()
+        .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    Ok(Html(html))
+}
+
+/// Save a new address to the book
+pub async fn create(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Form(form): Form<AddressRequest>,
+) -> AppResult<Redirect> {
+    let user = get_current_user(&state, &jar)
+        .await?
+        .ok_or(AppError::NotAuthenticated)?;
+
+    let id = uuid::Uuid::new_v4().to_string();
+    sqlx::query(
+        "INSERT INTO addresses (id, user_npub, name, street, city, country, zip, created_at) VALUES (?, ?, ?, ?, ?, ?, ?, CURRENT_TIMESTAMP)",
+    )
+    .bind(&id)
+    .bind(&user.npub)
+    .bind(&form.name)
+    .bind(&form.street)
+    .bind(&form.city)
+    .bind(&form.country)
+    .bind(&form.zip)
+    .execute(state.db.pool())
+    .await?;
+
+    Ok(Redirect::to("/addresses"))
+}
+
+/// Update an existing address
+pub async fn update(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Path(id): Path<String>,
+    Form(form): Form<AddressRequest>,
+) -> AppResult<Redirect> {
+    let user = get_current_user(&state, &jar)
+        .await?
+        .ok_or(AppError::NotAuthenticated)?;
+
+    sqlx::query(
+        "UPDATE addresses SET name = ?, street = ?, city = ?, country = ?, zip = ? WHERE id = ? AND user_npub = ?",
+    )
+    .bind(&form.name)
+    .bind(&form.street)
+    .bind(&form.city)
+    .bind(&form.country)
+    .bind(&form.zip)
+    .bind(&id)
+    .bind(&user.npub)
+    .execute(state.db.pool())
+    .await?;
+
+    Ok(Redirect::to("/addresses"))
+}
+
+/// Remove a saved address
+pub async fn delete(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Path(id): Path<String>,
+) -> AppResult<Redirect> {
+    let user = get_current_user(&state, &jar)
+        .await?
+        .ok_or(AppError::NotAuthenticated)?;
+
+    sqlx::query("DELETE FROM addresses WHERE id = ? AND user_npub = ?")
+        .bind(&id)
+        .bind(&user.npub)
+        .execute(state.db.pool())
+        .await?;
+
+    Ok(Redirect::to("/addresses"))
+}
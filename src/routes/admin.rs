@@ -2,16 +2,23 @@ use std::sync::Arc;
 
 use askama::Template;
 use axum::{
-    extract::{Path, State},
-    response::{Html, Redirect},
+    extract::{Path, Query, State},
+    response::{Html, Json, Redirect},
     Form,
 };
 use axum_extra::extract::CookieJar;
+use serde::Deserialize;
 
 use crate::error::{AppError, AppResult};
-use crate::models::{Dispute, DisputeResolution, Escrow, Order, ResolveDisputeRequest};
+use crate::models::{
+    Dispute, DisputeResolution, Escrow, Order, ResolveDisputeRequest, SellerApplication,
+    SellerApplicationStatus,
+};
 use crate::routes::auth::get_current_user;
-use crate::services::EscrowService;
+use crate::services::{
+    ApiTokenService, EscrowService, ReconciliationService, RefreshTokenService, StatsBucket,
+    StatsPoint, StatsService,
+};
 use crate::AppState;
 
 #[derive(Template)]
@@ -102,6 +109,44 @@ pub async fn dashboard(
     Ok(Html(html))
 }
 
+/// Longest range an operator can request in one call.
+const MAX_STATS_RANGE_DAYS: i64 = 365;
+
+#[derive(Debug, Deserialize)]
+pub struct AdminStatsQuery {
+    /// "day", "week", or "month". Defaults to "day".
+    #[serde(default)]
+    bucket: Option<String>,
+    /// How many trailing days to cover. Defaults to 30, capped at
+    /// `MAX_STATS_RANGE_DAYS`.
+    #[serde(default)]
+    range_days: Option<i64>,
+}
+
+/// Time-bucketed marketplace trend data — orders, GMV, fees, escrow
+/// held/released, disputes opened/resolved — for an operator to chart,
+/// rather than the single-snapshot counts `dashboard` shows.
+pub async fn stats(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Query(query): Query<AdminStatsQuery>,
+) -> AppResult<Json<Vec<StatsPoint>>> {
+    let user = get_current_user(&state, &jar)
+        .await?
+        .ok_or(AppError::NotAuthenticated)?;
+
+    if !user.is_admin() {
+        return Err(AppError::NotAuthorized);
+    }
+
+    let bucket: StatsBucket = query.bucket.as_deref().unwrap_or("day").parse()?;
+    let range_days = query.range_days.unwrap_or(30).clamp(1, MAX_STATS_RANGE_DAYS);
+
+    let points = StatsService::time_series(&state.db, bucket, range_days).await?;
+
+    Ok(Json(points))
+}
+
 /// List open disputes
 pub async fn disputes(
     State(state): State<Arc<AppState>>,
@@ -236,7 +281,15 @@ pub async fn resolve_dispute(
         DisputeResolution::from_str(&form.resolution).ok_or(AppError::InvalidResolution)?;
 
     // Resolve escrow
-    EscrowService::resolve_dispute(&state.db, &dispute.escrow_id, resolution).await?;
+    EscrowService::resolve_dispute(
+        &state.db,
+        &state.escrow_coordinator,
+        &state.escrow_events,
+        &dispute.escrow_id,
+        &user.npub,
+        resolution,
+    )
+    .await?;
 
     // Update dispute record
     sqlx::query(
@@ -251,3 +304,213 @@ pub async fn resolve_dispute(
 
     Ok(Redirect::to("/admin/disputes"))
 }
+
+#[derive(Template)]
+#[template(path = "admin/reconcile.html")]
+struct ReconcileTemplate {
+    title: String,
+    unsettled_deposits: Vec<crate::services::UnsettledDeposit>,
+    stuck_withdrawals: Vec<crate::services::StuckWithdrawal>,
+}
+
+/// Incoming/outgoing payment reconciliation: surfaces deposits the indexer
+/// hasn't credited yet and withdrawals still awaiting melt confirmation, so
+/// an operator can see at a glance what the connector hasn't settled.
+pub async fn reconcile(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+) -> AppResult<Html<String>> {
+    let user = get_current_user(&state, &jar)
+        .await?
+        .ok_or(AppError::NotAuthenticated)?;
+
+    if !user.is_admin() {
+        return Err(AppError::NotAuthorized);
+    }
+
+    let report = ReconciliationService::report(&state.db).await?;
+
+    let template = ReconcileTemplate {
+        title: "Payment Reconciliation".to_string(),
+        unsettled_deposits: report.unsettled_deposits,
+        stuck_withdrawals: report.stuck_withdrawals,
+    };
+
+    let html = template
+        .render()
+        .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    Ok(Html(html))
+}
+
+/// Revoke all of a user's refresh token families (both the cookie-session
+/// and API/JWT kinds) and kill their active sessions — used on confirmed
+/// dispute fraud to cut off a compromised or bad-faith account everywhere
+/// it's logged in, not just going forward.
+pub async fn revoke_user_sessions(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Path(npub): Path<String>,
+) -> AppResult<Redirect> {
+    let user = get_current_user(&state, &jar)
+        .await?
+        .ok_or(AppError::NotAuthenticated)?;
+
+    if !user.is_admin() {
+        return Err(AppError::NotAuthorized);
+    }
+
+    sqlx::query("DELETE FROM sessions WHERE user_npub = ?")
+        .bind(&npub)
+        .execute(state.db.pool())
+        .await?;
+
+    RefreshTokenService::revoke_all_for_user(&state.db, &npub).await?;
+    ApiTokenService::revoke_all_for_user(&state.db, &npub).await?;
+
+    Ok(Redirect::to("/admin/disputes"))
+}
+
+#[derive(Template)]
+#[template(path = "admin/seller_applications.html")]
+struct SellerApplicationsTemplate {
+    title: String,
+    applications: Vec<SellerApplication>,
+}
+
+/// List pending seller applications for admin review.
+pub async fn seller_applications(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+) -> AppResult<Html<String>> {
+    let user = get_current_user(&state, &jar)
+        .await?
+        .ok_or(AppError::NotAuthenticated)?;
+
+    if !user.is_admin() {
+        return Err(AppError::NotAuthorized);
+    }
+
+    let applications: Vec<SellerApplication> = sqlx::query_as(
+        "SELECT * FROM seller_applications WHERE status = 'applying' ORDER BY created_at ASC",
+    )
+    .fetch_all(state.db.pool())
+    .await?;
+
+    let template = SellerApplicationsTemplate {
+        title: "Seller Applications".to_string(),
+        applications,
+    };
+
+    let html = template
+        .render()
+        .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    Ok(Html(html))
+}
+
+/// Approve a pending application: promotes the applicant's role, grants
+/// the categories they requested (unbonded, unlike the self-service bond
+/// path), and seeds their seller stats row the same way `become_seller`
+/// does.
+pub async fn approve_seller_application(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Path(id): Path<String>,
+) -> AppResult<Redirect> {
+    let user = get_current_user(&state, &jar)
+        .await?
+        .ok_or(AppError::NotAuthenticated)?;
+
+    if !user.is_admin() {
+        return Err(AppError::NotAuthorized);
+    }
+
+    let application: SellerApplication =
+        sqlx::query_as("SELECT * FROM seller_applications WHERE id = ?")
+            .bind(&id)
+            .fetch_optional(state.db.pool())
+            .await?
+            .ok_or(AppError::SellerApplicationNotFound)?;
+
+    if application.status != SellerApplicationStatus::Applying {
+        return Err(AppError::SellerApplicationAlreadyDecided);
+    }
+
+    let mut tx = state.db.pool().begin().await?;
+
+    sqlx::query("UPDATE users SET role = 'seller' WHERE npub = ?")
+        .bind(&application.user_npub)
+        .execute(&mut *tx)
+        .await?;
+
+    for category in application.requested_categories() {
+        sqlx::query(
+            "INSERT OR REPLACE INTO seller_categories (npub, category, bond_paid, paid_at) VALUES (?, ?, 0, CURRENT_TIMESTAMP)",
+        )
+        .bind(&application.user_npub)
+        .bind(&category)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    sqlx::query(
+        "INSERT OR IGNORE INTO seller_stats (npub, total_sales, total_revenue, completed_orders, disputed_orders, dispute_rate) VALUES (?, 0, 0, 0, 0, 0.0)",
+    )
+    .bind(&application.user_npub)
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query(
+        "UPDATE seller_applications SET status = 'approved', decided_at = CURRENT_TIMESTAMP WHERE id = ?",
+    )
+    .bind(&id)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(Redirect::to("/admin/seller-applications"))
+}
+
+#[derive(Deserialize)]
+pub struct DenySellerApplicationForm {
+    pub reason: String,
+}
+
+/// Deny a pending application, recording why for the applicant to see.
+pub async fn deny_seller_application(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Path(id): Path<String>,
+    Form(form): Form<DenySellerApplicationForm>,
+) -> AppResult<Redirect> {
+    let user = get_current_user(&state, &jar)
+        .await?
+        .ok_or(AppError::NotAuthenticated)?;
+
+    if !user.is_admin() {
+        return Err(AppError::NotAuthorized);
+    }
+
+    let application: SellerApplication =
+        sqlx::query_as("SELECT * FROM seller_applications WHERE id = ?")
+            .bind(&id)
+            .fetch_optional(state.db.pool())
+            .await?
+            .ok_or(AppError::SellerApplicationNotFound)?;
+
+    if application.status != SellerApplicationStatus::Applying {
+        return Err(AppError::SellerApplicationAlreadyDecided);
+    }
+
+    sqlx::query(
+        "UPDATE seller_applications SET status = 'denied', denial_reason = ?, decided_at = CURRENT_TIMESTAMP WHERE id = ?",
+    )
+    .bind(&form.reason)
+    .bind(&id)
+    .execute(state.db.pool())
+    .await?;
+
+    Ok(Redirect::to("/admin/seller-applications"))
+}
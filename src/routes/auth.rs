@@ -1,20 +1,30 @@
+use std::net::SocketAddr;
 use std::sync::Arc;
 
 use askama::Template;
 use axum::{
-    extract::State,
-    response::{Html, Redirect},
+    extract::{ConnectInfo, State},
+    http::HeaderMap,
+    response::{Html, Json, Redirect},
     Form,
 };
 use axum_extra::extract::CookieJar;
 use chrono::{Duration, Utc};
-use serde::Deserialize;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
 
 use crate::error::{AppError, AppResult};
-use crate::services::NostrService;
+use crate::models::{LoginChallenge, User};
+use crate::services::{ApiTokenService, JwtService, NostrService, RefreshTokenService};
 use crate::AppState;
 
 const SESSION_COOKIE: &str = "session";
+const REFRESH_COOKIE: &str = "refresh_token";
+const CHALLENGE_COOKIE: &str = "login_challenge";
+
+/// How long an issued login challenge stays valid for a client to sign and
+/// post back.
+const CHALLENGE_TTL_MINUTES: i64 = 5;
 
 #[derive(Template)]
 #[template(path = "auth/login.html")]
@@ -32,17 +42,110 @@ struct RegisterTemplate {
     error: Option<String>,
 }
 
+#[derive(Serialize)]
+pub struct ChallengeResponse {
+    challenge: String,
+    /// Domain/relay tag the client must sign into the challenge event, so
+    /// it can't be replayed against a different site.
+    domain: String,
+}
+
 #[derive(Deserialize)]
 pub struct LoginForm {
+    /// Signed kind-22242 challenge/response event (JSON), as produced by a
+    /// NIP-07 extension or remote signer against the challenge from
+    /// `GET /auth/challenge`.
+    event: String,
+}
+
+#[derive(Deserialize)]
+pub struct DevLoginForm {
     nsec: String,
 }
 
+#[derive(Serialize)]
+pub struct TokenPair {
+    access_token: String,
+    refresh_token: String,
+}
+
+#[derive(Deserialize)]
+pub struct ApiRefreshForm {
+    refresh_token: String,
+}
+
 #[derive(Deserialize)]
 pub struct RegisterForm {
     nsec: Option<String>,
     generate_new: Option<String>,
 }
 
+/// Create a new access session plus a refresh token for `npub`, returning
+/// their ids/values ready to be set as cookies. `user_agent`/`ip_address`
+/// are stored purely as a label for the account-security session list
+/// (`routes::account`) to distinguish entries by — neither is trusted for
+/// any authorization decision.
+async fn create_session(
+    state: &AppState,
+    npub: &str,
+    user_agent: Option<&str>,
+    ip_address: Option<&str>,
+) -> AppResult<(String, String)> {
+    let session_id = uuid::Uuid::new_v4().to_string();
+    let expires_at = Utc::now() + Duration::hours(state.config.session_hours as i64);
+
+    sqlx::query(
+        "INSERT INTO sessions (id, user_npub, expires_at, created_at, last_seen_at, user_agent, ip_address) VALUES (?, ?, ?, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP, ?, ?)",
+    )
+    .bind(&session_id)
+    .bind(npub)
+    .bind(expires_at)
+    .bind(user_agent)
+    .bind(ip_address)
+    .execute(state.db.pool())
+    .await?;
+
+    let refresh_token = RefreshTokenService::issue(&state.db, npub).await?;
+
+    Ok((session_id, refresh_token))
+}
+
+/// Pull a coarse device label out of the request's `User-Agent` header, if
+/// present.
+fn user_agent_label(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(axum::http::header::USER_AGENT)
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.to_string())
+}
+
+fn session_cookie(value: String) -> axum_extra::extract::cookie::Cookie<'static> {
+    axum_extra::extract::cookie::Cookie::build((SESSION_COOKIE, value))
+        .path("/")
+        .http_only(true)
+        .secure(true) // Requires HTTPS (Tor hidden service)
+        .same_site(axum_extra::extract::cookie::SameSite::Strict)
+        .build()
+}
+
+fn refresh_cookie(value: String) -> axum_extra::extract::cookie::Cookie<'static> {
+    axum_extra::extract::cookie::Cookie::build((REFRESH_COOKIE, value))
+        .path("/auth/refresh")
+        .http_only(true)
+        .secure(true)
+        .same_site(axum_extra::extract::cookie::SameSite::Strict)
+        .build()
+}
+
+fn challenge_cookie(value: String) -> axum_extra::extract::cookie::Cookie<'static> {
+    axum_extra::extract::cookie::Cookie::build((CHALLENGE_COOKIE, value))
+        .path("/")
+        .http_only(true)
+        .secure(true)
+        .same_site(axum_extra::extract::cookie::SameSite::Strict)
+        .build()
+}
+
 /// Login page
 pub async fn login_page() -> AppResult<Html<String>> {
     let template = LoginTemplate {
@@ -57,58 +160,237 @@ pub async fn login_page() -> AppResult<Html<String>> {
     Ok(Html(html))
 }
 
-/// Handle login
-pub async fn login(
-    State(state): State<Arc<AppState>>,
-    jar: CookieJar,
-    Form(form): Form<LoginForm>,
-) -> AppResult<(CookieJar, Redirect)> {
-    // Validate nsec and get npub
-    let npub = NostrService::npub_from_nsec(&form.nsec)?;
-
-    // Check if user exists, create if not
-    let user_exists: Option<(String,)> =
-        sqlx::query_as("SELECT npub FROM users WHERE npub = ?")
-            .bind(&npub)
-            .fetch_optional(state.db.pool())
-            .await?;
+/// Find-or-create the user for `npub`, bump their last-active timestamp,
+/// and return the full row — shared by the cookie (`login_npub`) and
+/// bearer-token (`api_login`) paths, which differ only in what they mint
+/// once the npub is known.
+async fn find_or_create_user(state: &AppState, npub: &str) -> AppResult<User> {
+    let existing: Option<User> = sqlx::query_as("SELECT * FROM users WHERE npub = ?")
+        .bind(npub)
+        .fetch_optional(state.db.pool())
+        .await?;
 
-    if user_exists.is_none() {
-        // Create new user
+    if existing.is_none() {
         sqlx::query(
             "INSERT INTO users (npub, role, wallet_balance, last_active_at, created_at) VALUES (?, 'buyer', 0, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)",
         )
-        .bind(&npub)
+        .bind(npub)
         .execute(state.db.pool())
         .await?;
     }
 
-    // Update last active
     sqlx::query("UPDATE users SET last_active_at = CURRENT_TIMESTAMP WHERE npub = ?")
-        .bind(&npub)
+        .bind(npub)
         .execute(state.db.pool())
         .await?;
 
-    // Create session
-    let session_id = uuid::Uuid::new_v4().to_string();
-    let expires_at = Utc::now() + Duration::hours(state.config.session_hours as i64);
+    sqlx::query_as("SELECT * FROM users WHERE npub = ?")
+        .bind(npub)
+        .fetch_one(state.db.pool())
+        .await
+        .map_err(AppError::from)
+}
 
-    sqlx::query("INSERT INTO sessions (id, user_npub, expires_at, created_at) VALUES (?, ?, ?, CURRENT_TIMESTAMP)")
-        .bind(&session_id)
-        .bind(&npub)
-        .bind(expires_at)
+/// Find-or-create the user for `npub` and mint a session + refresh token —
+/// the part of logging in via the browser that's the same regardless of
+/// how the npub was proven.
+async fn login_npub(
+    state: &AppState,
+    npub: &str,
+    user_agent: Option<&str>,
+    ip_address: Option<&str>,
+) -> AppResult<(String, String)> {
+    find_or_create_user(state, npub).await?;
+    create_session(state, npub, user_agent, ip_address).await
+}
+
+/// Validate and consume the cookie-tracked login challenge against a
+/// posted-back signed event, returning the jar with the challenge cookie
+/// cleared and the npub the event was signed by. Shared by the cookie
+/// (`login`) and bearer-token (`api_login`) entry points, which only
+/// differ in what they do with the proven npub afterward.
+async fn consume_login_challenge(
+    state: &AppState,
+    jar: CookieJar,
+    event_json: &str,
+) -> AppResult<(CookieJar, String)> {
+    let challenge_id = jar
+        .get(CHALLENGE_COOKIE)
+        .map(|c| c.value().to_string())
+        .ok_or(AppError::InvalidLoginChallenge)?;
+
+    let challenge: LoginChallenge =
+        sqlx::query_as("SELECT * FROM login_challenges WHERE id = ?")
+            .bind(&challenge_id)
+            .fetch_optional(state.db.pool())
+            .await?
+            .ok_or(AppError::InvalidLoginChallenge)?;
+
+    // One-time use regardless of outcome, so a failed/replayed attempt
+    // can't be retried against the same challenge.
+    sqlx::query("DELETE FROM login_challenges WHERE id = ?")
+        .bind(&challenge_id)
         .execute(state.db.pool())
         .await?;
 
-    // Set session cookie
-    let cookie = axum_extra::extract::cookie::Cookie::build((SESSION_COOKIE, session_id))
-        .path("/")
-        .http_only(true)
-        .secure(true) // Requires HTTPS (Tor hidden service)
-        .same_site(axum_extra::extract::cookie::SameSite::Strict)
-        .build();
+    if challenge.is_expired() {
+        return Err(AppError::InvalidLoginChallenge);
+    }
+
+    let npub = NostrService::verify_challenge_event(
+        event_json,
+        &challenge.challenge,
+        &state.config.auth_challenge_domain,
+        Duration::minutes(CHALLENGE_TTL_MINUTES),
+    )?
+    .ok_or(AppError::InvalidLoginChallenge)?;
 
-    Ok((jar.add(cookie), Redirect::to("/")))
+    let jar = jar.remove(axum_extra::extract::cookie::Cookie::from(CHALLENGE_COOKIE));
+
+    Ok((jar, npub))
+}
+
+/// Issue a one-time login challenge for the client to sign. Stored
+/// server-side (keyed by a cookie holding its id) so `login` can check a
+/// posted-back event's `challenge` tag against exactly what was handed out,
+/// rather than trusting the client to echo it honestly.
+pub async fn challenge(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+) -> AppResult<(CookieJar, Json<ChallengeResponse>)> {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    let challenge_value = hex::encode(bytes);
+
+    let id = uuid::Uuid::new_v4().to_string();
+    let expires_at = Utc::now() + Duration::minutes(CHALLENGE_TTL_MINUTES);
+
+    sqlx::query(
+        "INSERT INTO login_challenges (id, challenge, expires_at, created_at) VALUES (?, ?, ?, CURRENT_TIMESTAMP)",
+    )
+    .bind(&id)
+    .bind(&challenge_value)
+    .bind(expires_at)
+    .execute(state.db.pool())
+    .await?;
+
+    Ok((
+        jar.add(challenge_cookie(id)),
+        Json(ChallengeResponse {
+            challenge: challenge_value,
+            domain: state.config.auth_challenge_domain.clone(),
+        }),
+    ))
+}
+
+/// Handle login via a signed NIP-42-style challenge/response event (kind
+/// 22242), proving control of an npub without the nsec ever reaching the
+/// server — the nsec stays in the user's NIP-07 extension or remote
+/// signer the whole time.
+pub async fn login(
+    State(state): State<Arc<AppState>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    jar: CookieJar,
+    Form(form): Form<LoginForm>,
+) -> AppResult<(CookieJar, Redirect)> {
+    let (jar, npub) = consume_login_challenge(&state, jar, &form.event).await?;
+    let (session_id, refresh_token) = login_npub(
+        &state,
+        &npub,
+        user_agent_label(&headers).as_deref(),
+        Some(&addr.ip().to_string()),
+    )
+    .await?;
+
+    Ok((
+        jar.add(session_cookie(session_id))
+            .add(refresh_cookie(refresh_token)),
+        Redirect::to("/"),
+    ))
+}
+
+/// Dev/test-only login that takes a raw nsec directly, bypassing the
+/// challenge/response flow entirely. Unreachable unless
+/// `Config::dev_login_enabled` is set, since handling a real nsec
+/// server-side is exactly what the challenge flow exists to avoid.
+pub async fn login_dev(
+    State(state): State<Arc<AppState>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    jar: CookieJar,
+    Form(form): Form<DevLoginForm>,
+) -> AppResult<(CookieJar, Redirect)> {
+    if !state.config.dev_login_enabled {
+        return Err(AppError::NotAuthorized);
+    }
+
+    let npub = NostrService::npub_from_nsec(&form.nsec)?;
+    let (session_id, refresh_token) = login_npub(
+        &state,
+        &npub,
+        user_agent_label(&headers).as_deref(),
+        Some(&addr.ip().to_string()),
+    )
+    .await?;
+
+    Ok((
+        jar.add(session_cookie(session_id)).add(refresh_cookie(refresh_token)),
+        Redirect::to("/"),
+    ))
+}
+
+/// Exchange a signed login challenge event for a JWT access token plus a
+/// long-lived API refresh token, for programmatic clients that authenticate
+/// with a `Bearer` header instead of carrying a browser session cookie.
+/// Proves the npub the exact same way the cookie flow's `login` does —
+/// this is just a different thing to mint at the end of it.
+pub async fn api_login(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Form(form): Form<LoginForm>,
+) -> AppResult<(CookieJar, Json<TokenPair>)> {
+    let (jar, npub) = consume_login_challenge(&state, jar, &form.event).await?;
+    let user = find_or_create_user(&state, &npub).await?;
+    let role = String::from(user.role);
+
+    let refresh = ApiTokenService::issue(&state.db, &npub, &role).await?;
+    let access_token = JwtService::issue(
+        state.config.session_secret.as_bytes(),
+        &npub,
+        &role,
+        &refresh.jti,
+    )?;
+
+    Ok((
+        jar,
+        Json(TokenPair {
+            access_token,
+            refresh_token: refresh.jti,
+        }),
+    ))
+}
+
+/// Rotate an API refresh token and mint a fresh access token, mirroring
+/// the cookie flow's `/auth/refresh` but for bearer clients: validates the
+/// presented token against the `tokens` table and rotates it (deletes the
+/// old jti, inserts a new one) so a replayed token can't be used twice.
+pub async fn api_refresh(
+    State(state): State<Arc<AppState>>,
+    Form(form): Form<ApiRefreshForm>,
+) -> AppResult<Json<TokenPair>> {
+    let refresh = ApiTokenService::rotate(&state.db, &form.refresh_token).await?;
+    let access_token = JwtService::issue(
+        state.config.session_secret.as_bytes(),
+        &refresh.user_npub,
+        &refresh.role,
+        &refresh.jti,
+    )?;
+
+    Ok(Json(TokenPair {
+        access_token,
+        refresh_token: refresh.jti,
+    }))
 }
 
 /// Register page
@@ -130,6 +412,8 @@ pub async fn register_page() -> AppResult<Html<String>> {
 /// Handle registration
 pub async fn register(
     State(state): State<Arc<AppState>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
     jar: CookieJar,
     Form(form): Form<RegisterForm>,
 ) -> AppResult<(CookieJar, Html<String>)> {
@@ -174,24 +458,14 @@ pub async fn register(
     .execute(state.db.pool())
     .await?;
 
-    // Create session
-    let session_id = uuid::Uuid::new_v4().to_string();
-    let expires_at = Utc::now() + Duration::hours(state.config.session_hours as i64);
-
-    sqlx::query("INSERT INTO sessions (id, user_npub, expires_at, created_at) VALUES (?, ?, ?, CURRENT_TIMESTAMP)")
-        .bind(&session_id)
-        .bind(&npub)
-        .bind(expires_at)
-        .execute(state.db.pool())
-        .await?;
-
-    // Set session cookie and redirect
-    let cookie = axum_extra::extract::cookie::Cookie::build((SESSION_COOKIE, session_id))
-        .path("/")
-        .http_only(true)
-        .secure(true)
-        .same_site(axum_extra::extract::cookie::SameSite::Strict)
-        .build();
+    // Create access session and refresh token
+    let (session_id, refresh_token) = create_session(
+        &state,
+        &npub,
+        user_agent_label(&headers).as_deref(),
+        Some(&addr.ip().to_string()),
+    )
+    .await?;
 
     // Show success page with reminder to save nsec
     let template = RegisterTemplate {
@@ -205,7 +479,10 @@ pub async fn register(
         .render()
         .map_err(|e| AppError::Internal(e.to_string()))?;
 
-    Ok((jar.add(cookie), Html(html)))
+    Ok((
+        jar.add(session_cookie(session_id)).add(refresh_cookie(refresh_token)),
+        Html(html),
+    ))
 }
 
 /// Handle logout
@@ -224,12 +501,57 @@ pub async fn logout(
             .await?;
     }
 
-    // Remove cookie
-    let jar = jar.remove(axum_extra::extract::cookie::Cookie::from(SESSION_COOKIE));
+    // Revoke the refresh token family so it can't be used to mint a new
+    // session after logout
+    if let Some(cookie) = jar.get(REFRESH_COOKIE) {
+        RefreshTokenService::revoke_by_token(&state.db, cookie.value()).await?;
+    }
+
+    // Remove cookies
+    let jar = jar
+        .remove(axum_extra::extract::cookie::Cookie::from(SESSION_COOKIE))
+        .remove(axum_extra::extract::cookie::Cookie::from(REFRESH_COOKIE));
 
     Ok((jar, Redirect::to("/")))
 }
 
+/// Refresh an expired/expiring access session using the refresh token
+/// cookie: validates and rotates it, revoking the whole token family if
+/// an already-rotated token is replayed (a theft signal).
+pub async fn refresh(
+    State(state): State<Arc<AppState>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    jar: CookieJar,
+) -> AppResult<(CookieJar, Redirect)> {
+    let refresh_token = jar
+        .get(REFRESH_COOKIE)
+        .map(|c| c.value().to_string())
+        .ok_or(AppError::InvalidRefreshToken)?;
+
+    let (npub, new_refresh_token) = RefreshTokenService::rotate(&state.db, &refresh_token).await?;
+
+    let session_id = uuid::Uuid::new_v4().to_string();
+    let expires_at = Utc::now() + Duration::hours(state.config.session_hours as i64);
+
+    sqlx::query(
+        "INSERT INTO sessions (id, user_npub, expires_at, created_at, last_seen_at, user_agent, ip_address) VALUES (?, ?, ?, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP, ?, ?)",
+    )
+    .bind(&session_id)
+    .bind(&npub)
+    .bind(expires_at)
+    .bind(user_agent_label(&headers))
+    .bind(addr.ip().to_string())
+    .execute(state.db.pool())
+    .await?;
+
+    Ok((
+        jar.add(session_cookie(session_id))
+            .add(refresh_cookie(new_refresh_token)),
+        Redirect::to("/"),
+    ))
+}
+
 /// Extract current user from session cookie (middleware helper)
 pub async fn get_current_user(
     state: &AppState,
@@ -265,7 +587,33 @@ pub async fn get_current_user(
             .bind(&session.user_npub)
             .execute(state.db.pool())
             .await?;
+
+        sqlx::query("UPDATE sessions SET last_seen_at = CURRENT_TIMESTAMP WHERE id = ?")
+            .bind(&session.id)
+            .execute(state.db.pool())
+            .await?;
     }
 
     Ok(user)
 }
+
+/// Require a NIP-98 auth event proving the caller signed this exact
+/// request with `npub`'s nsec, on top of the session cookie. Used for
+/// seller actions (e.g. `mark_shipped`, `buy_category`) where a stolen
+/// session cookie alone shouldn't be enough to act as the npub.
+pub fn require_nip98_auth(
+    npub: &str,
+    method: &str,
+    url: &str,
+    body: &[u8],
+    auth_event: &str,
+) -> AppResult<()> {
+    let verified =
+        NostrService::verify_auth_event(auth_event, npub, method, url, body, Duration::minutes(2))?;
+
+    if !verified {
+        return Err(AppError::NotAuthorized);
+    }
+
+    Ok(())
+}
@@ -11,9 +11,9 @@ use chrono::{Duration, Utc};
 use serde::Deserialize;
 
 use crate::error::{AppError, AppResult};
-use crate::models::{CartItem, CheckoutItem, CheckoutSession, Listing};
+use crate::models::{Address, CartItem, CheckoutItem, CheckoutSession, Listing, TransactionType};
 use crate::routes::auth::get_current_user;
-use crate::services::EscrowService;
+use crate::services::{EscrowService, LedgerService, RateService, ACCOUNT_MINT_FLOAT};
 use crate::AppState;
 
 #[derive(Template)]
@@ -39,6 +39,7 @@ struct CheckoutTemplate {
     items: Vec<CheckoutItemView>,
     time_remaining: i64,
     wallet_balance: i64,
+    addresses: Vec<Address>,
 }
 
 struct CheckoutItemView {
@@ -50,8 +51,18 @@ struct CheckoutItemView {
 pub struct CheckoutForm {
     payment_method: String, // "wallet" or "external"
     cashu_token: Option<String>,
+    /// Saved address to ship to, chosen in the checkout page's
+    /// address-selection step. Optional since not every order needs
+    /// shipping (e.g. a digital-only cart).
+    address_id: Option<String>,
+    /// Optional free-text instructions for the seller (gift message,
+    /// delivery preferences, etc.), capped at `MAX_NOTES_LEN` characters.
+    notes: Option<String>,
 }
 
+/// Longest buyer note accepted at checkout.
+const MAX_NOTES_LEN: usize = 500;
+
 /// Show cart
 pub async fn show(
     State(state): State<Arc<AppState>>,
@@ -77,7 +88,7 @@ pub async fn show(
             .fetch_one(state.db.pool())
             .await?;
 
-        subtotal += listing.price;
+        subtotal += listing.price * cart_item.quantity;
         items.push(CartItemView { cart_item, listing });
     }
 
@@ -120,7 +131,8 @@ pub async fn add(
         return Err(AppError::ListingNotAvailable);
     }
 
-    // Check not already in cart
+    // A second add for the same listing just bumps the line's quantity
+    // rather than erroring, so a buyer can order more than one unit.
     let existing: Option<(String,)> =
         sqlx::query_as("SELECT id FROM cart_items WHERE user_npub = ? AND listing_id = ?")
             .bind(&user.npub)
@@ -128,19 +140,21 @@ pub async fn add(
             .fetch_optional(state.db.pool())
             .await?;
 
-    if existing.is_some() {
-        return Err(AppError::ItemAlreadyInCart);
+    if let Some((item_id,)) = existing {
+        sqlx::query("UPDATE cart_items SET quantity = quantity + 1 WHERE id = ?")
+            .bind(&item_id)
+            .execute(state.db.pool())
+            .await?;
+    } else {
+        let id = uuid::Uuid::new_v4().to_string();
+        sqlx::query("INSERT INTO cart_items (id, user_npub, listing_id, added_at, quantity, quantity_unit) VALUES (?, ?, ?, CURRENT_TIMESTAMP, 1, 'piece')")
+            .bind(&id)
+            .bind(&user.npub)
+            .bind(&listing_id)
+            .execute(state.db.pool())
+            .await?;
     }
 
-    // Add to cart
-    let id = uuid::Uuid::new_v4().to_string();
-    sqlx::query("INSERT INTO cart_items (id, user_npub, listing_id, added_at) VALUES (?, ?, ?, CURRENT_TIMESTAMP)")
-        .bind(&id)
-        .bind(&user.npub)
-        .bind(&listing_id)
-        .execute(state.db.pool())
-        .await?;
-
     Ok(Redirect::to("/cart"))
 }
 
@@ -208,17 +222,32 @@ pub async fn checkout_page(
                 continue; // Skip unavailable items
             }
 
-            total_amount += listing.price;
+            // Listings priced in fiat are re-converted through the current
+            // rate at lock time, since `listing.price` may have drifted
+            // since the listing was created; the fiat figure itself is
+            // fixed so the buyer's receipt always shows what they agreed to.
+            let locked_price = if let Some(fiat_price) = listing.fiat_price_decimal() {
+                let rate = state.rate.current_rate(&state.db).await?;
+                RateService::fiat_to_sats(fiat_price, rate)?
+            } else {
+                listing.price
+            };
+
+            total_amount += locked_price * cart_item.quantity;
 
             let item_id = uuid::Uuid::new_v4().to_string();
             sqlx::query(
-                "INSERT INTO checkout_items (id, checkout_id, listing_id, seller_npub, locked_price) VALUES (?, ?, ?, ?, ?)",
+                "INSERT INTO checkout_items (id, checkout_id, listing_id, seller_npub, locked_price, locked_fiat_currency, locked_fiat_amount, quantity, quantity_unit) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
             )
             .bind(&item_id)
             .bind(&checkout_id)
             .bind(&listing.id)
             .bind(&listing.seller_npub)
-            .bind(listing.price)
+            .bind(locked_price)
+            .bind(&listing.fiat_currency)
+            .bind(&listing.fiat_price)
+            .bind(cart_item.quantity)
+            .bind(&cart_item.quantity_unit)
             .execute(state.db.pool())
             .await?;
         }
@@ -259,12 +288,19 @@ pub async fn checkout_page(
         item_views.push(CheckoutItemView { item, listing });
     }
 
+    let addresses: Vec<Address> =
+        sqlx::query_as("SELECT * FROM addresses WHERE user_npub = ? ORDER BY created_at DESC")
+            .bind(&user.npub)
+            .fetch_all(state.db.pool())
+            .await?;
+
     let template = CheckoutTemplate {
         title: "Checkout".to_string(),
         time_remaining: checkout.time_remaining(),
         wallet_balance: user.wallet_balance,
         checkout,
         items: item_views,
+        addresses,
     };
 
     let html = template
@@ -274,6 +310,89 @@ pub async fn checkout_page(
     Ok(Html(html))
 }
 
+/// Shared body for the checkout page's +/- quantity controls: adjusts one
+/// checkout item's `quantity` by `delta` (floored at 1, so the last unit
+/// can't be zeroed out from here — use `/cart/remove` for that on the cart
+/// page instead) and re-sums the parent session's `total_amount`/
+/// `fee_amount` from its locked per-unit prices so the checkout total
+/// stays consistent with what's displayed.
+async fn bump_checkout_item_quantity(
+    state: &Arc<AppState>,
+    user_npub: &str,
+    item_id: &str,
+    delta: i64,
+) -> AppResult<CheckoutSession> {
+    let item: CheckoutItem = sqlx::query_as(
+        "SELECT ci.* FROM checkout_items ci JOIN checkout_sessions cs ON cs.id = ci.checkout_id WHERE ci.id = ? AND cs.user_npub = ? AND cs.status = 'pending' AND cs.expires_at > CURRENT_TIMESTAMP",
+    )
+    .bind(item_id)
+    .bind(user_npub)
+    .fetch_optional(state.db.pool())
+    .await?
+    .ok_or(AppError::PriceLockExpired)?;
+
+    let new_quantity = (item.quantity + delta).max(1);
+
+    sqlx::query("UPDATE checkout_items SET quantity = ? WHERE id = ?")
+        .bind(new_quantity)
+        .bind(item_id)
+        .execute(state.db.pool())
+        .await?;
+
+    let (total_amount,): (i64,) = sqlx::query_as(
+        "SELECT COALESCE(SUM(locked_price * quantity), 0) FROM checkout_items WHERE checkout_id = ?",
+    )
+    .bind(&item.checkout_id)
+    .fetch_one(state.db.pool())
+    .await?;
+
+    let fee_amount = (total_amount * state.config.fee_percent as i64) / 100;
+
+    sqlx::query("UPDATE checkout_sessions SET total_amount = ?, fee_amount = ? WHERE id = ?")
+        .bind(total_amount)
+        .bind(fee_amount)
+        .bind(&item.checkout_id)
+        .execute(state.db.pool())
+        .await?;
+
+    let session: CheckoutSession = sqlx::query_as("SELECT * FROM checkout_sessions WHERE id = ?")
+        .bind(&item.checkout_id)
+        .fetch_one(state.db.pool())
+        .await?;
+
+    Ok(session)
+}
+
+/// Bump a checkout item's quantity up by one
+pub async fn increment_item(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Path(item_id): Path<String>,
+) -> AppResult<Redirect> {
+    let user = get_current_user(&state, &jar)
+        .await?
+        .ok_or(AppError::NotAuthenticated)?;
+
+    bump_checkout_item_quantity(&state, &user.npub, &item_id, 1).await?;
+
+    Ok(Redirect::to("/checkout"))
+}
+
+/// Bump a checkout item's quantity down by one (floored at 1)
+pub async fn decrement_item(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Path(item_id): Path<String>,
+) -> AppResult<Redirect> {
+    let user = get_current_user(&state, &jar)
+        .await?
+        .ok_or(AppError::NotAuthenticated)?;
+
+    bump_checkout_item_quantity(&state, &user.npub, &item_id, -1).await?;
+
+    Ok(Redirect::to("/checkout"))
+}
+
 /// Process checkout payment
 pub async fn checkout(
     State(state): State<Arc<AppState>>,
@@ -295,7 +414,49 @@ pub async fn checkout(
 
     let total = checkout.total_amount + checkout.fee_amount;
 
-    // Process payment
+    // Resolve the chosen address, if any, before opening the transaction —
+    // a read against the buyer's own address book, not a mutation.
+    let address: Option<Address> = if let Some(address_id) = &form.address_id {
+        let address: Address = sqlx::query_as("SELECT * FROM addresses WHERE id = ? AND user_npub = ?")
+            .bind(address_id)
+            .bind(&user.npub)
+            .fetch_optional(state.db.pool())
+            .await?
+            .ok_or(AppError::InvalidInput("Address not found".to_string()))?;
+        Some(address)
+    } else {
+        None
+    };
+
+    let notes = match &form.notes {
+        Some(notes) if notes.chars().count() > MAX_NOTES_LEN => {
+            return Err(AppError::InvalidInput(format!(
+                "Note must be {} characters or fewer",
+                MAX_NOTES_LEN
+            )))
+        }
+        Some(notes) if notes.trim().is_empty() => None,
+        other => other.clone(),
+    };
+
+    // Receiving an external Cashu token redeems it at the mint right away —
+    // that can't be rolled back, so it happens before the transaction below
+    // even opens, same as `create_escrow`'s own mint call further down.
+    let external_amount = match form.payment_method.as_str() {
+        "wallet" => None,
+        "external" => {
+            let token = form.cashu_token.clone().ok_or(AppError::InvalidCashuToken)?;
+            Some(state.cashu.receive_token(&token, Some(&user.npub)).await?)
+        }
+        _ => return Err(AppError::PaymentFailed("Invalid payment method".to_string())),
+    };
+
+    // Everything from here on is one transaction: if any step fails (e.g.
+    // escrow creation), the wallet deduction/credit, the checkout session's
+    // paid marker, and any orders/escrows already inserted this pass all
+    // roll back together, rather than leaving a charged buyer with no order.
+    let mut db_tx = state.db.pool().begin().await?;
+
     match form.payment_method.as_str() {
         "wallet" => {
             if user.wallet_balance < total {
@@ -305,17 +466,25 @@ pub async fn checkout(
                 });
             }
 
-            // Deduct from wallet
-            sqlx::query("UPDATE users SET wallet_balance = wallet_balance - ? WHERE npub = ?")
-                .bind(total)
-                .bind(&user.npub)
-                .execute(state.db.pool())
-                .await?;
+            // No debit for `total_amount` here: each seller group below
+            // calls `EscrowService::create_escrow`, which debits the
+            // buyer's wallet for that group's `seller_total` through its
+            // own `LedgerService::post`, and those debits sum to
+            // `total_amount`. Debiting that again here would charge the
+            // buyer twice. The platform fee isn't part of any seller's
+            // escrow, so it still needs its own debit.
+            LedgerService::post(
+                &mut db_tx,
+                &user.npub,
+                &state.config.admin_npub,
+                checkout.fee_amount,
+                &String::from(TransactionType::Fee),
+                Some(&checkout.id),
+            )
+            .await?;
         }
         "external" => {
-            // Receive Cashu token
-            let token = form.cashu_token.ok_or(AppError::InvalidCashuToken)?;
-            let amount = state.cashu.receive_tokens(&token).await?;
+            let amount = external_amount.expect("external payment always sets an amount");
 
             if (amount as i64) < total {
                 return Err(AppError::InsufficientBalanceDetails {
@@ -324,30 +493,51 @@ pub async fn checkout(
                 });
             }
 
-            // If overpaid, credit difference to wallet
-            if (amount as i64) > total {
-                let overpayment = amount as i64 - total;
-                sqlx::query("UPDATE users SET wallet_balance = wallet_balance + ? WHERE npub = ?")
-                    .bind(overpayment)
-                    .bind(&user.npub)
-                    .execute(state.db.pool())
-                    .await?;
-            }
+            // Credit the whole redeemed token to the buyer's wallet, not
+            // just the overpayment: `create_escrow`'s own debit still
+            // draws from the buyer's wallet balance (not the token), so
+            // it needs the full amount there to draw `total_amount` from.
+            // Any amount beyond `total` is left as wallet balance.
+            LedgerService::post(
+                &mut db_tx,
+                ACCOUNT_MINT_FLOAT,
+                &user.npub,
+                amount as i64,
+                &String::from(TransactionType::Deposit),
+                None,
+            )
+            .await?;
+
+            // Same platform-fee debit as the wallet branch: the credit
+            // above only covers the per-seller escrow debits further
+            // down, not the fee.
+            LedgerService::post(
+                &mut db_tx,
+                &user.npub,
+                &state.config.admin_npub,
+                checkout.fee_amount,
+                &String::from(TransactionType::Fee),
+                Some(&checkout.id),
+            )
+            .await?;
         }
-        _ => return Err(AppError::PaymentFailed("Invalid payment method".to_string())),
+        _ => unreachable!("payment method already validated above"),
     }
 
     // Mark checkout as paid
-    sqlx::query("UPDATE checkout_sessions SET status = 'paid', paid_at = CURRENT_TIMESTAMP WHERE id = ?")
-        .bind(&checkout.id)
-        .execute(state.db.pool())
-        .await?;
+    sqlx::query(
+        "UPDATE checkout_sessions SET status = 'paid', paid_at = CURRENT_TIMESTAMP, notes = ? WHERE id = ?",
+    )
+    .bind(&notes)
+    .bind(&checkout.id)
+    .execute(&mut *db_tx)
+    .await?;
 
     // Create escrows and orders grouped by seller
     let items: Vec<CheckoutItem> =
         sqlx::query_as("SELECT * FROM checkout_items WHERE checkout_id = ?")
             .bind(&checkout.id)
-            .fetch_all(state.db.pool())
+            .fetch_all(&mut *db_tx)
             .await?;
 
     // Group items by seller
@@ -362,11 +552,13 @@ pub async fn checkout(
 
     // Create one escrow and order per seller
     for (seller_npub, items) in seller_items {
-        let seller_total: i64 = items.iter().map(|i| i.locked_price).sum();
+        let seller_total: i64 = items.iter().map(|i| i.line_total()).sum();
 
         // Create escrow
         let escrow = EscrowService::create_escrow(
-            &state.db,
+            &mut db_tx,
+            state.cashu.primary_mint(),
+            &state.escrow_coordinator,
             &user.npub,
             &seller_npub,
             seller_total,
@@ -377,28 +569,64 @@ pub async fn checkout(
         // Create order
         let order_id = uuid::Uuid::new_v4().to_string();
         sqlx::query(
-            "INSERT INTO orders (id, checkout_id, buyer_npub, seller_npub, escrow_id, status, created_at) VALUES (?, ?, ?, ?, ?, 'pending', CURRENT_TIMESTAMP)",
+            "INSERT INTO orders (id, checkout_id, buyer_npub, seller_npub, escrow_id, status, notes, created_at) VALUES (?, ?, ?, ?, ?, 'pending', ?, CURRENT_TIMESTAMP)",
         )
         .bind(&order_id)
         .bind(&checkout.id)
         .bind(&user.npub)
         .bind(&seller_npub)
         .bind(&escrow.id)
-        .execute(state.db.pool())
+        .bind(&notes)
+        .execute(&mut *db_tx)
         .await?;
 
+        // Snapshot the chosen address against this order (so later edits to
+        // the address book don't change what the seller already saw), and
+        // encrypt a copy for the seller into each order item's
+        // encrypted_shipping. No buyer nsec is held server-side, so this
+        // goes through the coordinator's own keypair as sender, same as the
+        // escrow handshake DMs.
+        let encrypted_shipping = if let Some(address) = &address {
+            sqlx::query(
+                "INSERT INTO order_addresses (order_id, name, street, city, country, zip) VALUES (?, ?, ?, ?, ?, ?)",
+            )
+            .bind(&order_id)
+            .bind(&address.name)
+            .bind(&address.street)
+            .bind(&address.city)
+            .bind(&address.country)
+            .bind(&address.zip)
+            .execute(&mut *db_tx)
+            .await?;
+
+            let address_json = serde_json::json!({
+                "name": address.name,
+                "street": address.street,
+                "city": address.city,
+                "country": address.country,
+                "zip": address.zip,
+            })
+            .to_string();
+
+            Some(state.escrow_coordinator.encrypt_for(&seller_npub, &address_json)?)
+        } else {
+            None
+        };
+
         // Create order items
         for item in items {
             let item_id = uuid::Uuid::new_v4().to_string();
             sqlx::query(
-                "INSERT INTO order_items (id, order_id, listing_id, price, encrypted_shipping) VALUES (?, ?, ?, ?, ?)",
+                "INSERT INTO order_items (id, order_id, listing_id, price, encrypted_shipping, quantity, quantity_unit) VALUES (?, ?, ?, ?, ?, ?, ?)",
             )
             .bind(&item_id)
             .bind(&order_id)
             .bind(&item.listing_id)
             .bind(item.locked_price)
-            .bind(&item.encrypted_shipping)
-            .execute(state.db.pool())
+            .bind(&encrypted_shipping)
+            .bind(item.quantity)
+            .bind(&item.quantity_unit)
+            .execute(&mut *db_tx)
             .await?;
         }
     }
@@ -406,8 +634,33 @@ pub async fn checkout(
     // Clear cart
     sqlx::query("DELETE FROM cart_items WHERE user_npub = ?")
         .bind(&user.npub)
-        .execute(state.db.pool())
+        .execute(&mut *db_tx)
         .await?;
 
+    // Record the payment against the transaction ledger, tagged with the
+    // checkout it settled, so a buyer's receipt and seller payout
+    // reconciliation can find it by order reference later. Taken after the
+    // per-seller escrow debits above so `balance_after` reflects the
+    // buyer's actual post-checkout balance.
+    let (post_payment_balance,): (i64,) =
+        sqlx::query_as("SELECT wallet_balance FROM users WHERE npub = ?")
+            .bind(&user.npub)
+            .fetch_one(&mut *db_tx)
+            .await?;
+
+    sqlx::query(
+        "INSERT INTO wallet_transactions (id, user_npub, transaction_type, amount, balance_after, description, checkout_id, created_at) VALUES (?, ?, 'purchase', ?, ?, 'Checkout payment', ?, CURRENT_TIMESTAMP)",
+    )
+    .bind(uuid::Uuid::new_v4().to_string())
+    .bind(&user.npub)
+    .bind(-total)
+    .bind(post_payment_balance)
+    .bind(&checkout.id)
+    .execute(&mut *db_tx)
+    .await?;
+
+    db_tx.commit().await?;
+    state.escrow_events.notify_all();
+
     Ok(Redirect::to("/orders"))
 }
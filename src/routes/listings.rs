@@ -1,16 +1,20 @@
+use std::str::FromStr;
 use std::sync::Arc;
 
 use askama::Template;
 use axum::{
     extract::{Path, Query, State},
-    response::{Html, Redirect},
+    response::{Html, Json, Redirect},
     Form,
 };
 use axum_extra::extract::CookieJar;
+use rust_decimal::Decimal;
+use sqlx::{QueryBuilder, Sqlite};
 
 use crate::error::{AppError, AppResult};
 use crate::models::{CreateListingRequest, Listing, ListingSearchQuery};
 use crate::routes::auth::get_current_user;
+use crate::services::{RateService, RatingService, SellerReputation};
 use crate::AppState;
 
 #[derive(Template)]
@@ -42,55 +46,76 @@ struct NewListingTemplate {
     error: Option<String>,
 }
 
-/// List all listings with search/filter
-pub async fn index(
-    State(state): State<Arc<AppState>>,
-    Query(query): Query<ListingSearchQuery>,
-) -> AppResult<Html<String>> {
-    let offset = query.offset();
-    let limit = query.per_page();
+/// Quote `q` as a single FTS5 phrase so reserved query syntax (`NOT`,
+/// `OR`, bare `"`, column filters) in user input can't produce a
+/// malformed `MATCH` expression. This is bound as an ordinary parameter
+/// either way, so it's about query validity, not injection.
+fn fts_match_phrase(q: &str) -> String {
+    format!("\"{}\"", q.replace('"', "\"\""))
+}
 
-    // Build query based on filters
-    let mut sql = String::from(
-        "SELECT * FROM listings WHERE is_active = true AND expires_at > CURRENT_TIMESTAMP",
-    );
-    let mut count_sql =
-        String::from("SELECT COUNT(*) FROM listings WHERE is_active = true AND expires_at > CURRENT_TIMESTAMP");
+/// Push `select` onto `builder` (empty so far), followed by the `FROM`
+/// clause (with the FTS join when searching) and the shared
+/// `category`/price/`seller` filters.
+fn push_listings_query(builder: &mut QueryBuilder<Sqlite>, select: &str, query: &ListingSearchQuery) {
+    let search_term = query.q.as_deref().map(str::trim).filter(|q| !q.is_empty());
+
+    builder.push(select);
+
+    if let Some(q) = search_term {
+        builder.push(" FROM listings JOIN listings_fts ON listings.rowid = listings_fts.rowid WHERE listings_fts MATCH ");
+        builder.push_bind(fts_match_phrase(q));
+        builder.push(" AND listings.is_active = true AND listings.expires_at > CURRENT_TIMESTAMP");
+    } else {
+        builder.push(" FROM listings WHERE is_active = true AND expires_at > CURRENT_TIMESTAMP");
+    }
 
     if let Some(ref cat) = query.category {
-        sql.push_str(&format!(" AND category = '{}'", cat));
-        count_sql.push_str(&format!(" AND category = '{}'", cat));
+        builder.push(" AND category = ").push_bind(cat.clone());
     }
 
     if let Some(min) = query.min_price {
-        sql.push_str(&format!(" AND price >= {}", min));
-        count_sql.push_str(&format!(" AND price >= {}", min));
+        builder.push(" AND price >= ").push_bind(min);
     }
 
     if let Some(max) = query.max_price {
-        sql.push_str(&format!(" AND price <= {}", max));
-        count_sql.push_str(&format!(" AND price <= {}", max));
+        builder.push(" AND price <= ").push_bind(max);
     }
 
     if let Some(ref seller) = query.seller {
-        sql.push_str(&format!(" AND seller_npub = '{}'", seller));
-        count_sql.push_str(&format!(" AND seller_npub = '{}'", seller));
+        builder.push(" AND seller_npub = ").push_bind(seller.clone());
     }
+}
 
-    // TODO: Full-text search with FTS5
-    if let Some(ref _q) = query.q {
-        // sql.push_str(&format!(" AND id IN (SELECT rowid FROM listings_fts WHERE listings_fts MATCH '{}')", q));
-    }
+/// List all listings with search/filter. Filters are bound parameters via
+/// `QueryBuilder` rather than interpolated into the SQL string; when `q`
+/// is present, results come from a `listings_fts MATCH` join ranked by
+/// BM25 (`rank`) instead of `created_at DESC`.
+pub async fn index(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ListingSearchQuery>,
+) -> AppResult<Html<String>> {
+    let offset = query.offset();
+    let limit = query.per_page();
+    let searching = query.q.as_deref().map(str::trim).is_some_and(|q| !q.is_empty());
 
-    sql.push_str(" ORDER BY created_at DESC");
-    sql.push_str(&format!(" LIMIT {} OFFSET {}", limit, offset));
+    let mut sql: QueryBuilder<Sqlite> = QueryBuilder::new("");
+    push_listings_query(&mut sql, "SELECT listings.*", &query);
+
+    if searching {
+        sql.push(" ORDER BY listings_fts.rank");
+    } else {
+        sql.push(" ORDER BY created_at DESC");
+    }
+    sql.push(" LIMIT ").push_bind(limit as i64);
+    sql.push(" OFFSET ").push_bind(offset as i64);
 
-    let listings: Vec<Listing> = sqlx::query_as(&sql).fetch_all(state.db.pool()).await?;
+    let listings: Vec<Listing> = sql.build_query_as().fetch_all(state.db.pool()).await?;
 
-    // Get total count for pagination
-    let (total,): (i64,) = sqlx::query_as(&count_sql)
-        .fetch_one(state.db.pool())
-        .await?;
+    // Get total count for pagination, filtered the same way as the page itself.
+    let mut count_sql: QueryBuilder<Sqlite> = QueryBuilder::new("");
+    push_listings_query(&mut count_sql, "SELECT COUNT(*)", &query);
+    let (total,): (i64,) = count_sql.build_query_as().fetch_one(state.db.pool()).await?;
 
     let total_pages = ((total as u32) + limit - 1) / limit;
     let current_page = query.page();
@@ -168,6 +193,23 @@ pub async fn show(
     Ok(Html(html))
 }
 
+/// A seller's rating histogram and Bayesian-adjusted reputation score, for
+/// the listing/seller templates to render alongside `seller_rating`.
+pub async fn seller_reputation(
+    State(state): State<Arc<AppState>>,
+    Path(npub): Path<String>,
+) -> AppResult<Json<SellerReputation>> {
+    let reputation = RatingService::reputation(
+        &state.db,
+        &npub,
+        state.config.rating_prior_mean,
+        state.config.rating_prior_weight,
+    )
+    .await?;
+
+    Ok(Json(reputation))
+}
+
 /// New listing form
 pub async fn new_page(
     State(state): State<Arc<AppState>>,
@@ -230,24 +272,43 @@ pub async fn create(
         return Err(AppError::CategoryNotAuthorized);
     }
 
+    // A fiat price overrides the sats `price` field, converted through the
+    // current rate — `price` stays the authoritative settlement amount
+    // everywhere else in the schema.
+    let fiat_currency = form
+        .fiat_price
+        .as_ref()
+        .map(|_| form.fiat_currency.clone().unwrap_or_else(|| state.rate.currency().to_string()));
+
+    let price = if let Some(fiat_price) = &form.fiat_price {
+        let fiat_amount = Decimal::from_str(fiat_price)
+            .map_err(|_| AppError::InvalidInput("Invalid fiat price".to_string()))?;
+        let rate = state.rate.current_rate(&state.db).await?;
+        RateService::fiat_to_sats(fiat_amount, rate)?
+    } else {
+        form.price
+    };
+
     // Create listing
     let id = uuid::Uuid::new_v4().to_string();
     let expires_at = chrono::Utc::now() + chrono::Duration::days(30);
 
     sqlx::query(
         r#"
-        INSERT INTO listings (id, seller_npub, title, description, price, category, is_active, stock, created_at, updated_at, expires_at)
-        VALUES (?, ?, ?, ?, ?, ?, true, ?, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP, ?)
+        INSERT INTO listings (id, seller_npub, title, description, price, category, is_active, stock, created_at, updated_at, expires_at, fiat_currency, fiat_price)
+        VALUES (?, ?, ?, ?, ?, ?, true, ?, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP, ?, ?, ?)
         "#,
     )
     .bind(&id)
     .bind(&user.npub)
     .bind(&form.title)
     .bind(&form.description)
-    .bind(form.price)
+    .bind(price)
     .bind(&form.category)
     .bind(form.stock)
     .bind(expires_at)
+    .bind(&fiat_currency)
+    .bind(&form.fiat_price)
     .execute(state.db.pool())
     .await?;
 
@@ -1,3 +1,5 @@
+pub mod account;
+pub mod address;
 pub mod admin;
 pub mod auth;
 pub mod cart;
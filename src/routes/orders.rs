@@ -1,19 +1,24 @@
 use std::sync::Arc;
+use std::time::Duration;
 
 use askama::Template;
 use axum::{
-    extract::{Path, State},
-    response::{Html, Redirect},
+    extract::{Path, Query, State},
+    response::{Html, Json, Redirect},
     Form,
 };
 use axum_extra::extract::CookieJar;
+use serde::Deserialize;
 
 use crate::error::{AppError, AppResult};
-use crate::models::{CreateDisputeRequest, Escrow, Order, OrderItem};
+use crate::models::{CreateDisputeRequest, CreateRatingRequest, Escrow, EscrowEvent, Order, OrderItem};
 use crate::routes::auth::get_current_user;
-use crate::services::EscrowService;
+use crate::services::{EscrowEventService, EscrowService, RatingService};
 use crate::AppState;
 
+/// Longest a client can ask `events` to park before returning an empty list
+const MAX_EVENTS_TIMEOUT_SECS: u64 = 60;
+
 #[derive(Template)]
 #[template(path = "orders/index.html")]
 struct OrdersIndexTemplate {
@@ -158,8 +163,15 @@ pub async fn confirm(
         return Err(AppError::OrderAlreadyCompleted);
     }
 
-    // Release escrow
-    EscrowService::release_escrow(&state.db, &order.escrow_id).await?;
+    // Release escrow: buyer confirmation satisfies the plan's seller-payout branch
+    EscrowService::confirm_receipt(
+        &state.db,
+        &state.escrow_coordinator,
+        &state.escrow_events,
+        &order.escrow_id,
+        &user.npub,
+    )
+    .await?;
 
     // Delete order messages (privacy)
     sqlx::query("DELETE FROM order_messages WHERE order_id = ?")
@@ -197,7 +209,13 @@ pub async fn dispute(
     }
 
     // Mark escrow as disputed
-    EscrowService::mark_disputed(&state.db, &order.escrow_id).await?;
+    EscrowService::dispute(
+        &state.db,
+        &state.escrow_coordinator,
+        &state.escrow_events,
+        &order.escrow_id,
+    )
+    .await?;
 
     // Create dispute
     let dispute_id = uuid::Uuid::new_v4().to_string();
@@ -225,3 +243,79 @@ pub async fn dispute(
 
     Ok(Redirect::to(&format!("/orders/{}", id)))
 }
+
+/// Rate a completed order's seller
+pub async fn rate(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Path(id): Path<String>,
+    Form(form): Form<CreateRatingRequest>,
+) -> AppResult<Redirect> {
+    let user = get_current_user(&state, &jar)
+        .await?
+        .ok_or(AppError::NotAuthenticated)?;
+
+    let order: Order = sqlx::query_as("SELECT * FROM orders WHERE id = ?")
+        .bind(&id)
+        .fetch_optional(state.db.pool())
+        .await?
+        .ok_or(AppError::OrderNotFound)?;
+
+    RatingService::rate_order(&state.db, &order, &user.npub, form.rating, form.comment).await?;
+
+    Ok(Redirect::to(&format!("/orders/{}", id)))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EscrowEventsQuery {
+    /// Only return events with an id greater than this cursor. Defaults to
+    /// 0, i.e. "everything so far".
+    #[serde(default)]
+    since: i64,
+    /// Seconds to park the request if there's nothing newer than `since`
+    /// yet, before returning an empty list. Defaults to (and is capped at)
+    /// `MAX_EVENTS_TIMEOUT_SECS`.
+    #[serde(default)]
+    timeout: Option<u64>,
+}
+
+/// Long-poll an order's escrow for status-transition events newer than
+/// `?since=<cursor>`: returns immediately if any already exist, otherwise
+/// parks the request on the shared [`crate::services::EscrowEventBus`]
+/// until one is posted or `?timeout=<seconds>` elapses, then returns
+/// whatever (possibly empty) list resulted. Lets a seller client react to
+/// releases/disputes without repeatedly polling this page.
+pub async fn events(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Path(id): Path<String>,
+    Query(query): Query<EscrowEventsQuery>,
+) -> AppResult<Json<Vec<EscrowEvent>>> {
+    let user = get_current_user(&state, &jar)
+        .await?
+        .ok_or(AppError::NotAuthenticated)?;
+
+    let order: Order = sqlx::query_as("SELECT * FROM orders WHERE id = ?")
+        .bind(&id)
+        .fetch_optional(state.db.pool())
+        .await?
+        .ok_or(AppError::OrderNotFound)?;
+
+    // Either party to the order may watch its escrow
+    if order.buyer_npub != user.npub && order.seller_npub != user.npub {
+        return Err(AppError::NotAuthorized);
+    }
+
+    let timeout_secs = query.timeout.unwrap_or(MAX_EVENTS_TIMEOUT_SECS).min(MAX_EVENTS_TIMEOUT_SECS);
+
+    let events = EscrowEventService::poll(
+        &state.db,
+        &state.escrow_events,
+        &order.escrow_id,
+        query.since,
+        Duration::from_secs(timeout_secs),
+    )
+    .await?;
+
+    Ok(Json(events))
+}
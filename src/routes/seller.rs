@@ -10,10 +10,13 @@ use axum_extra::extract::CookieJar;
 use serde::Deserialize;
 
 use crate::error::{AppError, AppResult};
-use crate::models::{Listing, MarkShippedRequest, Order, SellerStats};
-use crate::routes::auth::get_current_user;
+use crate::models::{Listing, MarkShippedRequest, Order, SellerApplication, SellerStats};
+use crate::routes::auth::{get_current_user, require_nip98_auth};
 use crate::AppState;
 
+/// Category ids `seller_applications`/`seller_categories` recognize.
+const VALID_CATEGORIES: &[&str] = &["digital", "physical", "services"];
+
 #[derive(Template)]
 #[template(path = "seller/dashboard.html")]
 struct DashboardTemplate {
@@ -168,6 +171,14 @@ pub async fn mark_shipped(
         return Err(AppError::NotAuthorized);
     }
 
+    require_nip98_auth(
+        &user.npub,
+        "POST",
+        &format!("/seller/orders/{}/ship", id),
+        form.tracking_info.as_deref().unwrap_or("").as_bytes(),
+        &form.auth_event,
+    )?;
+
     if !order.can_ship() {
         return Err(AppError::OrderAlreadyCompleted);
     }
@@ -319,6 +330,111 @@ pub async fn become_seller(
     Ok(Redirect::to("/seller/dashboard"))
 }
 
+#[derive(Template)]
+#[template(path = "seller/apply.html")]
+struct SellerApplyTemplate {
+    title: String,
+    application: Option<SellerApplication>,
+}
+
+/// Apply-to-sell page: shows the form if the buyer has no pending/decided
+/// application yet, or their most recent application's status otherwise.
+pub async fn apply_page(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+) -> AppResult<Html<String>> {
+    let user = get_current_user(&state, &jar)
+        .await?
+        .ok_or(AppError::NotAuthenticated)?;
+
+    if user.is_seller() {
+        return Err(AppError::Redirect("/seller/dashboard".to_string()));
+    }
+
+    let application: Option<SellerApplication> = sqlx::query_as(
+        "SELECT * FROM seller_applications WHERE user_npub = ? ORDER BY created_at DESC LIMIT 1",
+    )
+    .bind(&user.npub)
+    .fetch_optional(state.db.pool())
+    .await?;
+
+    let template = SellerApplyTemplate {
+        title: "Apply to Sell".to_string(),
+        application,
+    };
+
+    let html = template
+        .render()
+        .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    Ok(Html(html))
+}
+
+#[derive(Deserialize)]
+pub struct SellerApplyForm {
+    /// Comma-separated category ids being requested, e.g. "digital,services".
+    pub categories: String,
+}
+
+/// Submit an application to become a seller for admin review, instead of
+/// the instant bond-payment path in `become_seller`.
+pub async fn apply(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Form(form): Form<SellerApplyForm>,
+) -> AppResult<Redirect> {
+    let user = get_current_user(&state, &jar)
+        .await?
+        .ok_or(AppError::NotAuthenticated)?;
+
+    if user.is_seller() {
+        return Ok(Redirect::to("/seller/dashboard"));
+    }
+
+    let pending: Option<(String,)> = sqlx::query_as(
+        "SELECT id FROM seller_applications WHERE user_npub = ? AND status = 'applying'",
+    )
+    .bind(&user.npub)
+    .fetch_optional(state.db.pool())
+    .await?;
+
+    if pending.is_some() {
+        return Err(AppError::SellerApplicationPending);
+    }
+
+    let categories: Vec<&str> = form
+        .categories
+        .split(',')
+        .map(|c| c.trim())
+        .filter(|c| !c.is_empty())
+        .collect();
+
+    if categories.is_empty() {
+        return Err(AppError::InvalidInput(
+            "At least one category is required".to_string(),
+        ));
+    }
+
+    if categories.iter().any(|c| !VALID_CATEGORIES.contains(c)) {
+        return Err(AppError::InvalidCategory);
+    }
+
+    let requested_categories =
+        serde_json::to_string(&categories).map_err(|e| AppError::Internal(e.to_string()))?;
+
+    let id = uuid::Uuid::new_v4().to_string();
+    sqlx::query(
+        "INSERT INTO seller_applications (id, user_npub, requested_categories, status, created_at) VALUES (?, ?, ?, 'applying', CURRENT_TIMESTAMP)",
+    )
+    .bind(&id)
+    .bind(&user.npub)
+    .bind(&requested_categories)
+    .execute(state.db.pool())
+    .await?;
+
+    Ok(Redirect::to("/seller/apply"))
+}
+
 #[derive(Template)]
 #[template(path = "seller/categories.html")]
 struct CategoriesTemplate {
@@ -390,6 +506,8 @@ pub async fn categories_page(
 #[derive(Deserialize)]
 pub struct BuyCategoryForm {
     pub category: String,
+    /// NIP-98 auth event (JSON) proving the seller signed this exact request
+    pub auth_event: String,
 }
 
 /// Buy access to a new category
@@ -406,6 +524,14 @@ pub async fn buy_category(
         return Err(AppError::NotASeller);
     }
 
+    require_nip98_auth(
+        &user.npub,
+        "POST",
+        "/seller/categories/buy",
+        form.category.as_bytes(),
+        &form.auth_event,
+    )?;
+
     // Check if already has this category
     let existing: Option<(String,)> =
         sqlx::query_as("SELECT category FROM seller_categories WHERE npub = ? AND category = ?")
@@ -12,6 +12,7 @@ use serde::Deserialize;
 use crate::error::{AppError, AppResult};
 use crate::models::WalletTransaction;
 use crate::routes::auth::get_current_user;
+use crate::services::{LedgerService, ReconciliationService, TransactionFilter, ACCOUNT_MINT_FLOAT};
 use crate::AppState;
 
 #[derive(Template)]
@@ -48,6 +49,20 @@ pub struct DepositForm {
 pub struct WithdrawForm {
     amount: u64,
     invoice: String,
+    /// Optional tag (e.g. an order reference) for payout reconciliation,
+    /// see [`crate::services::LedgerService::get_transactions_by_label`].
+    label: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ExportForm {
+    amount: u64,
+    label: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct NwcConnectForm {
+    label: Option<String>,
 }
 
 /// Show wallet balance and transactions
@@ -59,12 +74,12 @@ pub async fn show(
         .await?
         .ok_or(AppError::NotAuthenticated)?;
 
-    let transactions: Vec<WalletTransaction> = sqlx::query_as(
-        "SELECT * FROM wallet_transactions WHERE user_npub = ? ORDER BY created_at DESC LIMIT 50",
-    )
-    .bind(&user.npub)
-    .fetch_all(state.db.pool())
-    .await?;
+    let transactions: Vec<WalletTransaction> =
+        LedgerService::get_transactions(&state.db, &user.npub, &TransactionFilter::default())
+            .await?
+            .into_iter()
+            .take(50)
+            .collect();
 
     let template = WalletTemplate {
         title: "Wallet".to_string(),
@@ -113,17 +128,27 @@ pub async fn deposit(
 
     // If Cashu token provided, receive it directly
     if let Some(token) = form.cashu_token {
-        let amount = state.cashu.receive_tokens(&token).await?;
+        let amount = state.cashu.receive_token(&token, Some(&user.npub)).await?;
+
+        // Credit wallet and post the ledger entries in one transaction, so
+        // a concurrent request never observes a half-applied balance
+        let mut db_tx = state.db.pool().begin().await?;
+        LedgerService::post(
+            &mut db_tx,
+            ACCOUNT_MINT_FLOAT,
+            &user.npub,
+            amount as i64,
+            "deposit",
+            None,
+        )
+        .await?;
 
-        // Credit wallet
-        let new_balance = user.wallet_balance + amount as i64;
-        sqlx::query("UPDATE users SET wallet_balance = ? WHERE npub = ?")
-            .bind(new_balance)
-            .bind(&user.npub)
-            .execute(state.db.pool())
-            .await?;
+        let (new_balance,): (i64,) =
+            sqlx::query_as("SELECT wallet_balance FROM users WHERE npub = ?")
+                .bind(&user.npub)
+                .fetch_one(&mut *db_tx)
+                .await?;
 
-        // Log transaction
         let tx_id = uuid::Uuid::new_v4().to_string();
         sqlx::query(
             "INSERT INTO wallet_transactions (id, user_npub, transaction_type, amount, balance_after, description, created_at) VALUES (?, ?, 'deposit', ?, ?, 'Cashu token deposit', CURRENT_TIMESTAMP)",
@@ -132,9 +157,11 @@ pub async fn deposit(
         .bind(&user.npub)
         .bind(amount as i64)
         .bind(new_balance)
-        .execute(state.db.pool())
+        .execute(&mut *db_tx)
         .await?;
 
+        db_tx.commit().await?;
+
         return Ok(Html(format!(
             "<p>Deposited {} sats. New balance: {} sats</p><a href=\"/wallet\">Back to Wallet</a>",
             amount, new_balance
@@ -143,7 +170,21 @@ pub async fn deposit(
 
     // Generate Lightning invoice if amount specified
     if let Some(amount) = form.amount {
-        let invoice = state.cashu.create_deposit_invoice(amount).await?;
+        let invoice = state.cashu.create_invoice(amount).await?;
+
+        // Hand the quote id to the deposit indexer so the background scan
+        // credits the wallet as soon as the invoice is paid, instead of
+        // requiring the user to come back with a token.
+        state
+            .deposit_indexer
+            .track(
+                &state.db,
+                &invoice.payment_hash,
+                &user.npub,
+                amount,
+                &invoice.connector_label,
+            )
+            .await?;
 
         let template = DepositTemplate {
             title: "Deposit".to_string(),
@@ -211,28 +252,109 @@ pub async fn withdraw(
         });
     }
 
-    // Pay Lightning invoice
-    state.cashu.withdraw(&form.invoice, form.amount).await?;
+    // Hold the sats, attempt the Lightning melt, and only finalize or
+    // reverse the ledger entry once the outcome is known - see
+    // ReconciliationService for why this replaced an optimistic
+    // pay-then-deduct that could leave the user debited for a melt that
+    // later failed or stayed pending.
+    ReconciliationService::withdraw(
+        &state.db,
+        &state.cashu,
+        &user.npub,
+        form.amount,
+        &form.invoice,
+        form.label.as_deref(),
+    )
+    .await?;
 
-    // Deduct from wallet
-    let new_balance = user.wallet_balance - form.amount as i64;
-    sqlx::query("UPDATE users SET wallet_balance = ? WHERE npub = ?")
-        .bind(new_balance)
-        .bind(&user.npub)
-        .execute(state.db.pool())
-        .await?;
+    Ok(Redirect::to("/wallet"))
+}
+
+/// Export value out of the marketplace as a real Cashu token: mints fresh
+/// blinded-and-unblinded proofs for `amount` sats from the wallet's pooled
+/// balance (via [`crate::services::CashuService::create_tokens`]) and
+/// hands the user a `cashuA...` string they hold and can redeem at any
+/// mint, rather than only being able to cash out through Lightning.
+pub async fn export(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Form(form): Form<ExportForm>,
+) -> AppResult<Html<String>> {
+    let user = get_current_user(&state, &jar)
+        .await?
+        .ok_or(AppError::NotAuthenticated)?;
+
+    if user.wallet_balance < form.amount as i64 {
+        return Err(AppError::InsufficientBalanceDetails {
+            needed: form.amount,
+            available: user.wallet_balance as u64,
+        });
+    }
+
+    // Token export is a Cashu-specific operation (minting real bearer
+    // proofs from the pooled balance) with no equivalent on a non-Cashu
+    // backend, so it targets the primary mint rather than the router.
+    let token = state.cashu.primary_mint().create_tokens(form.amount).await?;
+
+    // Deduct from wallet and post the ledger entries in one transaction, so
+    // a concurrent request never observes a half-applied balance
+    let mut db_tx = state.db.pool().begin().await?;
+    LedgerService::post(
+        &mut db_tx,
+        &user.npub,
+        ACCOUNT_MINT_FLOAT,
+        form.amount as i64,
+        "export",
+        None,
+    )
+    .await?;
+
+    let (new_balance,): (i64,) =
+        sqlx::query_as("SELECT wallet_balance FROM users WHERE npub = ?")
+            .bind(&user.npub)
+            .fetch_one(&mut *db_tx)
+            .await?;
 
-    // Log transaction
     let tx_id = uuid::Uuid::new_v4().to_string();
     sqlx::query(
-        "INSERT INTO wallet_transactions (id, user_npub, transaction_type, amount, balance_after, description, created_at) VALUES (?, ?, 'withdraw', ?, ?, 'Lightning withdrawal', CURRENT_TIMESTAMP)",
+        "INSERT INTO wallet_transactions (id, user_npub, transaction_type, amount, balance_after, description, label, created_at) VALUES (?, ?, 'export', ?, ?, 'Cashu token export', ?, CURRENT_TIMESTAMP)",
     )
     .bind(&tx_id)
     .bind(&user.npub)
     .bind(-(form.amount as i64))
     .bind(new_balance)
-    .execute(state.db.pool())
+    .bind(&form.label)
+    .execute(&mut *db_tx)
     .await?;
 
-    Ok(Redirect::to("/wallet"))
+    db_tx.commit().await?;
+
+    Ok(Html(format!(
+        "<p>Exported {} sats as a Cashu token. Save this token — it is the only copy:</p><pre>{}</pre><a href=\"/wallet\">Back to Wallet</a>",
+        form.amount, token
+    )))
+}
+
+/// Mint a Nostr Wallet Connect URI for an external NWC-capable wallet app.
+/// Only the app's public key is ever stored (see
+/// [`crate::services::NwcService::create_connection`]) — the URI returned
+/// here is the only time its secret half is shown.
+pub async fn nwc_connect(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Form(form): Form<NwcConnectForm>,
+) -> AppResult<Html<String>> {
+    let user = get_current_user(&state, &jar)
+        .await?
+        .ok_or(AppError::NotAuthenticated)?;
+
+    let uri = state
+        .nwc
+        .create_connection(&state.db, &user.npub, form.label.as_deref())
+        .await?;
+
+    Ok(Html(format!(
+        "<p>Connect an NWC-capable wallet app with this URI. It is shown only once:</p><pre>{}</pre><a href=\"/wallet\">Back to Wallet</a>",
+        uri
+    )))
 }
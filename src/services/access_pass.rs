@@ -0,0 +1,58 @@
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+const FIELD_SEP: char = ':';
+
+/// Signs and verifies time-boxed browsing-fee access passes. Redeeming a
+/// Cashu token worth enough sats buys a self-contained, HMAC-signed
+/// credential (no DB row, no per-request redemption) good until an expiry
+/// derived from how much was paid, mirroring the pay-for-access model of a
+/// paid relay.
+pub struct AccessPassService;
+
+impl AccessPassService {
+    /// Issue a pass good until `expires_at`, signed with `signing_key`.
+    pub fn issue(signing_key: &[u8], expires_at: DateTime<Utc>) -> String {
+        let expires_unix = expires_at.timestamp();
+        let signature = Self::sign(signing_key, expires_unix);
+        format!("{expires_unix}{FIELD_SEP}{signature}")
+    }
+
+    /// Verify `credential`'s signature and return its expiry if it's
+    /// well-formed, correctly signed, and not already expired.
+    pub fn verify(signing_key: &[u8], credential: &str) -> Option<DateTime<Utc>> {
+        let (expires_unix, signature) = credential.split_once(FIELD_SEP)?;
+        let expires_unix: i64 = expires_unix.parse().ok()?;
+
+        let expected = Self::sign(signing_key, expires_unix);
+        if !constant_time_eq(signature.as_bytes(), expected.as_bytes()) {
+            return None;
+        }
+
+        let expires_at = DateTime::from_timestamp(expires_unix, 0)?;
+        if expires_at < Utc::now() {
+            return None;
+        }
+
+        Some(expires_at)
+    }
+
+    fn sign(signing_key: &[u8], expires_unix: i64) -> String {
+        let mut mac =
+            HmacSha256::new_from_slice(signing_key).expect("HMAC accepts any key length");
+        mac.update(expires_unix.to_string().as_bytes());
+        hex::encode(mac.finalize().into_bytes())
+    }
+}
+
+/// Constant-time comparison so a mismatched signature can't be brute-forced
+/// one byte at a time via response timing.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
@@ -0,0 +1,75 @@
+use chrono::{Duration, Utc};
+
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::models::ApiToken;
+
+/// How long an issued API refresh token stays valid if never used.
+const API_REFRESH_TOKEN_DAYS: i64 = 30;
+
+/// Issues and rotates the long-lived refresh tokens backing `JwtService`'s
+/// short-lived access tokens, mirroring `RefreshTokenService`'s
+/// rotate-on-use scheme but for bearer/API clients instead of the
+/// browser's cookie session.
+pub struct ApiTokenService;
+
+impl ApiTokenService {
+    /// Issue a fresh refresh token for `user_npub`, returning the `ApiToken`
+    /// row (its `jti` is the bearer value handed to the client).
+    pub async fn issue(db: &Database, user_npub: &str, role: &str) -> AppResult<ApiToken> {
+        let jti = uuid::Uuid::new_v4().to_string();
+        let issued_at = Utc::now();
+        let expires_at = issued_at + Duration::days(API_REFRESH_TOKEN_DAYS);
+
+        sqlx::query(
+            "INSERT INTO tokens (jti, user_npub, role, issued_at, expires_at) VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(&jti)
+        .bind(user_npub)
+        .bind(role)
+        .bind(issued_at)
+        .bind(expires_at)
+        .execute(db.pool())
+        .await?;
+
+        Ok(ApiToken {
+            jti,
+            user_npub: user_npub.to_string(),
+            role: role.to_string(),
+            issued_at,
+            expires_at,
+        })
+    }
+
+    /// Validate `jti` against the table, delete it, and issue a new one for
+    /// the same user — a presented jti is good for exactly one refresh.
+    pub async fn rotate(db: &Database, jti: &str) -> AppResult<ApiToken> {
+        let record: ApiToken = sqlx::query_as("SELECT * FROM tokens WHERE jti = ?")
+            .bind(jti)
+            .fetch_optional(db.pool())
+            .await?
+            .ok_or(AppError::InvalidRefreshToken)?;
+
+        sqlx::query("DELETE FROM tokens WHERE jti = ?")
+            .bind(jti)
+            .execute(db.pool())
+            .await?;
+
+        if record.is_expired() {
+            return Err(AppError::InvalidRefreshToken);
+        }
+
+        Self::issue(db, &record.user_npub, &record.role).await
+    }
+
+    /// Revoke every outstanding API refresh token for a user — called
+    /// alongside `RefreshTokenService::revoke_all_for_user` so an admin
+    /// cutting off a disputed account kills its API access too.
+    pub async fn revoke_all_for_user(db: &Database, user_npub: &str) -> AppResult<()> {
+        sqlx::query("DELETE FROM tokens WHERE user_npub = ?")
+            .bind(user_npub)
+            .execute(db.pool())
+            .await?;
+        Ok(())
+    }
+}
@@ -2,35 +2,97 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
-use cdk::nuts::{CurrencyUnit, MintQuoteState, Token};
+use argon2::Argon2;
+use bip39::{Language, Mnemonic};
+use cdk::nuts::{CurrencyUnit, MeltQuoteState, MintQuoteState, PublicKey as CashuPublicKey, Token};
 use cdk::wallet::{ReceiveOptions, Wallet, WalletBuilder};
 use cdk::Amount;
 use cdk_sqlite::WalletSqliteDatabase;
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
 
 use crate::config::Config;
+use crate::db::Database;
 use crate::error::{AppError, AppResult};
+use crate::models::TransactionType;
+use crate::services::{LedgerService, NostrService, ACCOUNT_MINT_FLOAT};
+
+/// Version byte identifying the Argon2id + ChaCha20-Poly1305 encrypted
+/// wallet backup format (`salt(16) || nonce(12) || ciphertext+tag`).
+const BACKUP_ENC_VERSION: u8 = 1;
+const BACKUP_SALT_LEN: usize = 16;
+const BACKUP_NONCE_LEN: usize = 12;
+
+/// Argon2id params for deriving a backup's encryption key from its
+/// passphrase: 64 MiB memory, 3 iterations, 1 lane, matching the
+/// password-hardening used for nsec-at-rest encryption.
+fn backup_argon2() -> Argon2<'static> {
+    Argon2::new(
+        argon2::Algorithm::Argon2id,
+        argon2::Version::V0x13,
+        argon2::Params::new(65536, 3, 1, Some(32)).expect("valid Argon2 params"),
+    )
+}
+
+/// Plaintext contents of an encrypted wallet backup: enough seed material
+/// to restore the wallet via [`CashuService::restore`] plus the mint
+/// context it was paired with, so a restored wallet reconnects to the
+/// same mint and unit it was backed up from.
+#[derive(Serialize, Deserialize)]
+struct WalletBackup {
+    mnemonic: Option<String>,
+    seed_hex: Option<String>,
+    mint_url: String,
+    unit: String,
+}
 
 /// Cashu wallet service using an external mint (e.g., Minibits)
 ///
 /// This service manages ecash operations through CDK wallet.
 /// It connects to an external Cashu mint for real token operations.
 /// Mock mode is available for offline testing.
+///
+/// Blind-signature minting/unblinding and mint-enforced double-spend
+/// protection are already provided by the underlying CDK [`Wallet`] for
+/// every token this service creates or receives. The custodial gap this
+/// service introduces is architectural, not cryptographic: all users
+/// share a single pooled wallet here, tracked against an integer
+/// `wallet_balance` column rather than each user holding their own
+/// proofs.
 pub struct CashuService {
     /// CDK wallet instance
     wallet: Option<Arc<Wallet>>,
     /// Mint URL
     mint_url: String,
+    /// Wallet data directory, kept around so `export_mnemonic` can find the
+    /// persisted mnemonic file after construction.
+    data_dir: String,
+    /// Configured unit (e.g. "sat", "msat"), carried along for
+    /// [`Self::export_encrypted`] backups.
+    unit: String,
     /// Pending mint quotes (quote_id -> amount)
     pending_quotes: Arc<RwLock<HashMap<String, u64>>>,
     /// Mock mode for offline testing
     mock_mode: bool,
     /// Mock spent tokens (for mock mode only)
     mock_spent_tokens: Arc<RwLock<HashMap<String, bool>>>,
+    /// Mint URLs we'll accept ecash from via a melt/mint bridge, beyond
+    /// our own (see [`Self::receive_foreign_token`]).
+    trusted_mints: Vec<String>,
+    /// Per-mint CDK `Wallet` cache, keyed by mint URL, for swapping tokens
+    /// issued by a trusted-but-foreign mint without rebuilding a wallet
+    /// (and its on-disk store) on every incoming token.
+    foreign_wallets: Arc<RwLock<HashMap<String, Arc<Wallet>>>>,
+    /// Handle used to persist the melt->mint bridge's in-flight state (see
+    /// [`Self::receive_foreign_token`] and [`Self::sweep_pending_bridge_mints`]).
+    db: Database,
 }
 
 impl CashuService {
     /// Initialize Cashu service with external mint
-    pub async fn new(config: &Config) -> anyhow::Result<Self> {
+    pub async fn new(config: &Config, db: Database) -> anyhow::Result<Self> {
         let mint_url = config.mint.url.clone();
         let mock_mode = mint_url.is_empty() || mint_url == "mock";
 
@@ -39,9 +101,14 @@ impl CashuService {
             return Ok(Self {
                 wallet: None,
                 mint_url: "mock".to_string(),
+                data_dir: String::new(),
+                unit: config.mint.unit.clone(),
                 pending_quotes: Arc::new(RwLock::new(HashMap::new())),
                 mock_mode: true,
                 mock_spent_tokens: Arc::new(RwLock::new(HashMap::new())),
+                trusted_mints: config.trusted_mints.clone(),
+                foreign_wallets: Arc::new(RwLock::new(HashMap::new())),
+                db,
             });
         }
 
@@ -74,12 +141,30 @@ impl CashuService {
         Ok(Self {
             wallet: Some(Arc::new(wallet)),
             mint_url,
+            data_dir: config.mint.data_dir.clone(),
+            unit: config.mint.unit.clone(),
             pending_quotes: Arc::new(RwLock::new(HashMap::new())),
             mock_mode: false,
             mock_spent_tokens: Arc::new(RwLock::new(HashMap::new())),
+            trusted_mints: config.trusted_mints.clone(),
+            foreign_wallets: Arc::new(RwLock::new(HashMap::new())),
+            db,
         })
     }
 
+    /// Restore a wallet from a previously exported BIP39 mnemonic (see
+    /// [`Self::export_mnemonic`]), connecting to the same mint configured
+    /// in `config`. Overwrites any seed material already on disk in the
+    /// configured data directory.
+    pub async fn restore(config: &Config, mnemonic: &str, db: Database) -> anyhow::Result<Self> {
+        let phrase = Mnemonic::parse_in_normalized(Language::English, mnemonic.trim())?;
+
+        std::fs::create_dir_all(&config.mint.data_dir)?;
+        std::fs::write(Self::mnemonic_path(&config.mint.data_dir), phrase.to_string())?;
+
+        Self::new(config, db).await
+    }
+
     /// Create a Lightning invoice for deposit (mint quote)
     pub async fn create_deposit_invoice(&self, amount_sats: u64) -> AppResult<DepositInvoice> {
         if self.mock_mode {
@@ -111,6 +196,7 @@ impl CashuService {
             payment_hash: quote_id,
             amount_sats,
             expires_at,
+            connector_label: self.mint_url.clone(),
         })
     }
 
@@ -168,8 +254,19 @@ impl CashuService {
         Ok(token_str)
     }
 
-    /// Receive and validate Cashu tokens from external source
-    pub async fn receive_tokens(&self, token_str: &str) -> AppResult<u64> {
+    /// Receive and validate Cashu tokens from external source. Tokens
+    /// issued by our own mint are received directly; tokens from a
+    /// different, operator-trusted mint are bridged in via
+    /// [`Self::receive_foreign_token`] instead of being rejected outright.
+    ///
+    /// `credit_npub` names the user this redemption is ultimately for, if
+    /// any — the bridge path persists it so a crash between the foreign
+    /// melt and the home mint call can still credit the right buyer on
+    /// recovery (see [`Self::sweep_pending_bridge_mints`]) instead of just
+    /// restoring the operator's own backing proofs. Pass `None` when no
+    /// single user is on the other end of the redemption (e.g. an
+    /// anonymous browsing-fee token).
+    pub async fn receive_tokens(&self, token_str: &str, credit_npub: Option<&str>) -> AppResult<u64> {
         if self.mock_mode {
             return self.mock_receive_tokens(token_str).await;
         }
@@ -178,6 +275,18 @@ impl CashuService {
             AppError::Internal("Wallet not initialized".to_string())
         })?;
 
+        let token: Token = token_str.parse().map_err(|_| AppError::InvalidCashuToken)?;
+        let token_mint_url = token
+            .mint_url()
+            .map_err(|_| AppError::InvalidCashuToken)?
+            .to_string();
+
+        if token_mint_url != self.mint_url {
+            return self
+                .receive_foreign_token(&token_mint_url, token_str, credit_npub)
+                .await;
+        }
+
         // Receive (swap) tokens through the mint
         let amount = wallet
             .receive(token_str, ReceiveOptions::default())
@@ -190,42 +299,359 @@ impl CashuService {
         Ok(u64::from(amount))
     }
 
-    /// Withdraw to Lightning invoice (melt tokens)
+    /// Accept ecash issued by a mint other than our own: melt the foreign
+    /// proofs against the foreign mint to pay a bolt11 invoice our home
+    /// mint generates via a mint quote, then mint the equivalent home-mint
+    /// proofs once that invoice is paid - a melt -> mint bridge. The home
+    /// invoice is quoted net of the foreign mint's lightning fee_reserve,
+    /// so the amount credited reflects what actually lands at home rather
+    /// than the foreign token's face value.
+    async fn receive_foreign_token(
+        &self,
+        mint_url: &str,
+        token_str: &str,
+        credit_npub: Option<&str>,
+    ) -> AppResult<u64> {
+        if !self.trusted_mints.iter().any(|m| m == mint_url) {
+            tracing::warn!("rejected cross-mint token from untrusted mint: {}", mint_url);
+            return Err(AppError::InvalidCashuToken);
+        }
+
+        let home_wallet = self.wallet.as_ref().ok_or_else(|| {
+            AppError::Internal("Wallet not initialized".to_string())
+        })?;
+
+        let foreign_wallet = self.foreign_wallet(mint_url).await?;
+
+        let foreign_amount = foreign_wallet
+            .receive(token_str, ReceiveOptions::default())
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to receive cross-mint token: {}", e);
+                AppError::InvalidCashuToken
+            })?;
+        let foreign_amount_sats = u64::from(foreign_amount);
+
+        // Quote the full amount first just to learn the foreign mint's
+        // fee_reserve for paying our own invoice, then re-quote at home
+        // for the net amount so we only mint what we'll actually keep.
+        let probe_quote = home_wallet
+            .mint_quote(Amount::from(foreign_amount_sats), None)
+            .await
+            .map_err(|e| AppError::PaymentFailed(e.to_string()))?;
+        let probe_melt = foreign_wallet
+            .melt_quote(probe_quote.request.clone(), None)
+            .await
+            .map_err(|e| AppError::PaymentFailed(e.to_string()))?;
+
+        let fee_reserve_sats = u64::from(probe_melt.fee_reserve);
+        let net_sats = foreign_amount_sats.checked_sub(fee_reserve_sats).ok_or_else(|| {
+            AppError::PaymentFailed("foreign mint fee exceeds token amount".to_string())
+        })?;
+
+        let (mint_quote, melt_quote) = if fee_reserve_sats == 0 {
+            (probe_quote, probe_melt)
+        } else {
+            let mint_quote = home_wallet
+                .mint_quote(Amount::from(net_sats), None)
+                .await
+                .map_err(|e| AppError::PaymentFailed(e.to_string()))?;
+            let melt_quote = foreign_wallet
+                .melt_quote(mint_quote.request.clone(), None)
+                .await
+                .map_err(|e| AppError::PaymentFailed(e.to_string()))?;
+            (mint_quote, melt_quote)
+        };
+
+        // Record the bridge before spending the foreign proofs: once
+        // `melt()` below returns `Ok`, the foreign ecash is gone for good,
+        // so if the process dies before the home `mint()` call completes,
+        // this row is the only way `sweep_pending_bridge_mints` can later
+        // find the paid-but-uncredited home invoice and mint it instead of
+        // losing the sats outright.
+        let bridge_id = uuid::Uuid::new_v4().to_string();
+        sqlx::query(
+            "INSERT INTO pending_bridge_mints (id, mint_url, home_mint_quote_id, foreign_melt_quote_id, amount_sats, status, user_npub, created_at) VALUES (?, ?, ?, ?, ?, 'pending_melt', ?, CURRENT_TIMESTAMP)",
+        )
+        .bind(&bridge_id)
+        .bind(mint_url)
+        .bind(&mint_quote.id)
+        .bind(&melt_quote.id)
+        .bind(net_sats as i64)
+        .bind(credit_npub)
+        .execute(self.db.pool())
+        .await?;
+
+        foreign_wallet.melt(&melt_quote.id).await.map_err(|e| {
+            AppError::PaymentFailed(e.to_string())
+        })?;
+
+        sqlx::query("UPDATE pending_bridge_mints SET status = 'melted' WHERE id = ?")
+            .bind(&bridge_id)
+            .execute(self.db.pool())
+            .await?;
+
+        let proofs = home_wallet
+            .mint(&mint_quote.id, cdk::amount::SplitTarget::default(), None)
+            .await
+            .map_err(|e| AppError::PaymentFailed(e.to_string()))?;
+
+        let credited: u64 = proofs.iter().map(|p| u64::from(p.amount)).sum();
+
+        sqlx::query(
+            "UPDATE pending_bridge_mints SET status = 'credited', credited_at = CURRENT_TIMESTAMP WHERE id = ?",
+        )
+        .bind(&bridge_id)
+        .execute(self.db.pool())
+        .await?;
+
+        Ok(credited)
+    }
+
+    /// Recover any melt->mint bridge that crashed between the foreign melt
+    /// completing and the home proofs being minted (see
+    /// [`Self::receive_foreign_token`]): for every bridge still
+    /// `pending_melt` or `melted`, check whether the home mint quote it
+    /// paid actually settled and, if so, mint the proofs it's still owed.
+    /// A crash in that window means the caller that would normally credit
+    /// the paying buyer with `receive_foreign_token`'s return value never
+    /// ran, so once the proofs are recovered here, this also posts the
+    /// bridge's `user_npub` (if any) its `amount_sats` via
+    /// [`LedgerService::post`] — minting the proofs alone would only
+    /// restore the operator's own backing, leaving the buyer stranded.
+    /// Bridges with no `user_npub` (no single user was on the other end)
+    /// are minted back but left for manual reconciliation. Returns how
+    /// many bridges were recovered.
+    pub async fn sweep_pending_bridge_mints(&self) -> AppResult<u32> {
+        if self.mock_mode {
+            return Ok(0);
+        }
+
+        let wallet = self.wallet.as_ref().ok_or_else(|| {
+            AppError::Internal("Wallet not initialized".to_string())
+        })?;
+
+        let pending: Vec<(String, String, i64, Option<String>)> = sqlx::query_as(
+            "SELECT id, home_mint_quote_id, amount_sats, user_npub FROM pending_bridge_mints WHERE status IN ('pending_melt', 'melted')",
+        )
+        .fetch_all(self.db.pool())
+        .await?;
+
+        let mut recovered = 0u32;
+        for (id, quote_id, amount_sats, user_npub) in pending {
+            let status = match wallet.mint_quote_state(&quote_id).await {
+                Ok(status) => status,
+                Err(e) => {
+                    tracing::warn!("failed to check bridge mint quote {}: {}", quote_id, e);
+                    continue;
+                }
+            };
+
+            if status.state != MintQuoteState::Paid {
+                continue;
+            }
+
+            match wallet
+                .mint(&quote_id, cdk::amount::SplitTarget::default(), None)
+                .await
+            {
+                Ok(_) => {
+                    if let Some(user_npub) = &user_npub {
+                        let mut db_tx = self.db.pool().begin().await?;
+                        LedgerService::post(
+                            &mut db_tx,
+                            ACCOUNT_MINT_FLOAT,
+                            user_npub,
+                            amount_sats,
+                            &String::from(TransactionType::Deposit),
+                            Some(&id),
+                        )
+                        .await?;
+                        sqlx::query(
+                            "UPDATE pending_bridge_mints SET status = 'credited', credited_at = CURRENT_TIMESTAMP WHERE id = ?",
+                        )
+                        .bind(&id)
+                        .execute(&mut *db_tx)
+                        .await?;
+                        db_tx.commit().await?;
+                        tracing::info!(
+                            "recovered paid-but-uncredited bridge mint {} and credited {}",
+                            id,
+                            user_npub
+                        );
+                    } else {
+                        sqlx::query(
+                            "UPDATE pending_bridge_mints SET status = 'credited', credited_at = CURRENT_TIMESTAMP WHERE id = ?",
+                        )
+                        .bind(&id)
+                        .execute(self.db.pool())
+                        .await?;
+                        tracing::warn!(
+                            "recovered bridge mint {} with no user to credit; left for manual reconciliation",
+                            id
+                        );
+                    }
+                    recovered += 1;
+                }
+                Err(e) => {
+                    tracing::warn!("bridge mint {} still not mintable: {}", id, e);
+                }
+            }
+        }
+
+        Ok(recovered)
+    }
+
+    /// Get or build the cached CDK `Wallet` for a trusted foreign mint,
+    /// storing its proof database alongside the home wallet's own data
+    /// directory (keyed by a hash of the mint URL) and deriving it from
+    /// the same seed, so a restored wallet backup carries the same
+    /// cross-mint reach without separate key material to track.
+    async fn foreign_wallet(&self, mint_url: &str) -> AppResult<Arc<Wallet>> {
+        if let Some(wallet) = self.foreign_wallets.read().await.get(mint_url) {
+            return Ok(Arc::clone(wallet));
+        }
+
+        let mut wallets = self.foreign_wallets.write().await;
+        if let Some(wallet) = wallets.get(mint_url) {
+            return Ok(Arc::clone(wallet));
+        }
+
+        let data_dir = format!("{}/foreign-{}", self.data_dir, Self::token_id(mint_url));
+        std::fs::create_dir_all(&data_dir)
+            .map_err(|e| AppError::Internal(format!("Failed to create foreign mint data dir: {}", e)))?;
+
+        let db_path = format!("{}/wallet.db", data_dir);
+        let localstore = WalletSqliteDatabase::new(db_path.as_str())
+            .await
+            .map_err(|e| AppError::Internal(format!("Failed to open foreign mint wallet db: {}", e)))?;
+
+        let seed = Self::get_or_create_seed(&self.data_dir)
+            .map_err(|e| AppError::Internal(format!("Failed to load wallet seed: {}", e)))?;
+
+        let wallet = WalletBuilder::new()
+            .mint_url(mint_url.parse().map_err(|e| {
+                AppError::Internal(format!("Invalid mint URL: {}", e))
+            })?)
+            .unit(CurrencyUnit::Sat)
+            .localstore(Arc::new(localstore))
+            .seed(seed)
+            .build()
+            .map_err(|e| AppError::Internal(format!("Failed to build foreign mint wallet: {}", e)))?;
+
+        let wallet = Arc::new(wallet);
+        wallets.insert(mint_url.to_string(), Arc::clone(&wallet));
+        Ok(wallet)
+    }
+
+    /// Withdraw to Lightning invoice (melt tokens). Quotes and executes in
+    /// one call; callers that need to persist the quote id before
+    /// attempting payment (so a crash or timeout mid-melt can later be
+    /// checked rather than blindly retried — see
+    /// [`ReconciliationService::withdraw`](crate::services::ReconciliationService::withdraw))
+    /// should use [`Self::create_melt_quote`] and [`Self::execute_melt_quote`]
+    /// directly instead.
     pub async fn withdraw(&self, invoice: &str, amount_sats: u64) -> AppResult<WithdrawalResult> {
         if self.mock_mode {
             return self.mock_withdraw(amount_sats).await;
         }
 
+        let (quote_id, fee_reserve) = self.create_melt_quote(invoice).await?;
+        self.execute_melt_quote(&quote_id, amount_sats, fee_reserve).await
+    }
+
+    /// Quote a Lightning withdrawal without paying it yet, returning its
+    /// quote id and fee reserve, so the caller can persist the quote id
+    /// before calling [`Self::execute_melt_quote`] — see [`Self::withdraw`].
+    pub async fn create_melt_quote(&self, invoice: &str) -> AppResult<(String, u64)> {
+        if self.mock_mode {
+            return Ok((format!("mock-melt-{}", uuid::Uuid::new_v4()), 0));
+        }
+
         let wallet = self.wallet.as_ref().ok_or_else(|| {
             AppError::Internal("Wallet not initialized".to_string())
         })?;
 
-        // Validate invoice format
         if !invoice.starts_with("lnbc") && !invoice.starts_with("lntb") {
             return Err(AppError::WithdrawalFailed(
                 "Invalid Lightning invoice format".to_string(),
             ));
         }
 
-        // Create melt quote
         let quote = wallet
             .melt_quote(invoice.to_string(), None)
             .await
             .map_err(|e| AppError::WithdrawalFailed(e.to_string()))?;
 
-        // Execute melt
-        let melt_response = wallet
-            .melt(&quote.id)
+        Ok((quote.id, u64::from(quote.fee_reserve)))
+    }
+
+    /// Pay a quote previously created with [`Self::create_melt_quote`]. A
+    /// `melt()` call can return `Err` after the HTLC was already sent out
+    /// (a disconnect or timeout waiting on settlement, not a genuine
+    /// failure to pay), so on error this checks the quote's actual status
+    /// via [`Self::check_melt_paid`] before propagating — otherwise a
+    /// caller that reacts to the `Err` by retrying or falling back to a
+    /// different mint would pay the same invoice twice.
+    pub async fn execute_melt_quote(
+        &self,
+        quote_id: &str,
+        amount_sats: u64,
+        fee_reserve: u64,
+    ) -> AppResult<WithdrawalResult> {
+        if self.mock_mode {
+            return self.mock_withdraw(amount_sats).await;
+        }
+
+        let wallet = self.wallet.as_ref().ok_or_else(|| {
+            AppError::Internal("Wallet not initialized".to_string())
+        })?;
+
+        match wallet.melt(quote_id).await {
+            Ok(melt_response) => Ok(WithdrawalResult {
+                preimage: melt_response
+                    .preimage
+                    .unwrap_or_else(|| "unknown".to_string()),
+                amount_paid: amount_sats,
+                fee_paid: fee_reserve,
+            }),
+            Err(e) => {
+                if self.check_melt_paid(quote_id).await.unwrap_or(false) {
+                    tracing::warn!(
+                        "melt({}) returned an error but the quote already paid; treating the withdrawal as settled: {}",
+                        quote_id,
+                        e
+                    );
+                    return Ok(WithdrawalResult {
+                        preimage: "unknown".to_string(),
+                        amount_paid: amount_sats,
+                        fee_paid: 0,
+                    });
+                }
+
+                Err(AppError::WithdrawalFailed(e.to_string()))
+            }
+        }
+    }
+
+    /// Check whether a melt quote actually paid, despite a prior `Err`
+    /// from [`Self::execute_melt_quote`] — see that method and
+    /// [`ReconciliationService::retry_stuck_withdrawals`](crate::services::ReconciliationService::retry_stuck_withdrawals).
+    pub async fn check_melt_paid(&self, quote_id: &str) -> AppResult<bool> {
+        if self.mock_mode {
+            return Ok(false);
+        }
+
+        let wallet = self.wallet.as_ref().ok_or_else(|| {
+            AppError::Internal("Wallet not initialized".to_string())
+        })?;
+
+        let status = wallet
+            .melt_quote_status(quote_id)
             .await
             .map_err(|e| AppError::WithdrawalFailed(e.to_string()))?;
 
-        Ok(WithdrawalResult {
-            preimage: melt_response
-                .preimage
-                .unwrap_or_else(|| "unknown".to_string()),
-            amount_paid: amount_sats,
-            fee_paid: u64::from(quote.fee_reserve),
-        })
+        Ok(status.state == MeltQuoteState::Paid)
     }
 
     /// Get wallet balance
@@ -248,7 +674,7 @@ impl CashuService {
     /// Validate a browsing token (X-Cashu header)
     pub async fn validate_browsing_token(&self, token_str: &str) -> AppResult<BrowsingTokenInfo> {
         // For browsing tokens, we receive them (which validates and claims)
-        let amount = self.receive_tokens(token_str).await?;
+        let amount = self.receive_tokens(token_str, None).await?;
 
         if amount < 10 {
             return Err(AppError::InvalidBrowsingToken);
@@ -260,6 +686,30 @@ impl CashuService {
         })
     }
 
+    /// Derive a stable hash of a token's proof secrets, for replay
+    /// protection independent of how the token happens to be serialized.
+    /// Mock tokens carry no real proofs, so mock mode falls back to
+    /// hashing the token string itself (same as [`Self::token_id`]).
+    pub fn token_hash(&self, token_str: &str) -> AppResult<String> {
+        if self.mock_mode {
+            return Ok(Self::token_id(token_str));
+        }
+
+        use sha2::{Digest, Sha256};
+
+        let token: Token = token_str.parse().map_err(|_| AppError::InvalidCashuToken)?;
+
+        let mut secrets: Vec<String> = token
+            .proofs()
+            .into_values()
+            .flatten()
+            .map(|proof| proof.secret.to_string())
+            .collect();
+        secrets.sort();
+
+        Ok(hex::encode(Sha256::digest(secrets.join(",").as_bytes())))
+    }
+
     /// Create tokens for a user (from wallet balance)
     pub async fn create_tokens(&self, amount_sats: u64) -> AppResult<String> {
         if self.mock_mode {
@@ -288,6 +738,55 @@ impl CashuService {
         Ok(token.to_string())
     }
 
+    /// Create a NUT-11 P2PK-locked token for escrow: a 2-of-3 multisig
+    /// across `buyer_npub`/`seller_npub`/`arbiter_npub`, with `locktime`
+    /// after which the seller's key alone satisfies the condition (refund
+    /// path for an expired escrow).
+    ///
+    /// Only implemented in mock mode. Actually spending real wallet proofs
+    /// into this lock requires a release path that can later produce the
+    /// 2-of-3 P2PK signature/swap `DisputeResolution::calculate_release_plan`
+    /// describes — that execution doesn't exist yet (it needs the vendored
+    /// CDK signing primitives), so `EscrowService::create_escrow` never
+    /// calls this in real mode; it would otherwise burn real backing sats
+    /// into a token nothing can ever redeem. Calling it directly in real
+    /// mode is refused for the same reason, rather than silently spending.
+    pub async fn create_escrow_lock(
+        &self,
+        amount_sats: u64,
+        buyer_npub: &str,
+        seller_npub: &str,
+        arbiter_npub: &str,
+        locktime: chrono::DateTime<chrono::Utc>,
+    ) -> AppResult<String> {
+        if !self.mock_mode {
+            return Err(AppError::Internal(
+                "non-custodial escrow locking is not implemented for real mints yet (no release-plan execution to ever redeem the locked token) — escrow falls back to internal wallet-balance bookkeeping instead of calling this".to_string(),
+            ));
+        }
+
+        // Validated even in mock mode so a bad npub fails the same way it
+        // would against a real mint.
+        Self::npub_to_cashu_pubkey(seller_npub)?;
+        Self::npub_to_cashu_pubkey(buyer_npub)?;
+        Self::npub_to_cashu_pubkey(arbiter_npub)?;
+        let _ = locktime;
+
+        let random = Self::generate_hash();
+        Ok(format!("cashuA{}_{}_p2pk_mock", amount_sats, &random[..32]))
+    }
+
+    /// Derive a Cashu (secp256k1, even-parity) public key from a Nostr
+    /// npub's x-only public key, for use in NUT-11 P2PK conditions
+    fn npub_to_cashu_pubkey(npub: &str) -> AppResult<CashuPublicKey> {
+        let pubkey = NostrService::validate_npub(npub)?;
+        let mut compressed = [0u8; 33];
+        compressed[0] = 0x02;
+        compressed[1..].copy_from_slice(&pubkey.to_bytes());
+        CashuPublicKey::from_slice(&compressed)
+            .map_err(|e| AppError::Internal(format!("Invalid pubkey: {}", e)))
+    }
+
     /// Get mint info
     pub fn mint_info(&self) -> MintInfo {
         MintInfo {
@@ -301,6 +800,139 @@ impl CashuService {
         self.mock_mode
     }
 
+    /// Export the BIP39 mnemonic backing this wallet's seed, for an
+    /// operator to write down and later hand to [`Self::restore`] on a new
+    /// machine. Errs if the wallet predates mnemonic support and still
+    /// only has a raw seed file on disk.
+    pub fn export_mnemonic(&self) -> AppResult<String> {
+        if self.mock_mode {
+            return Err(AppError::Internal(
+                "no wallet seed in mock mode".to_string(),
+            ));
+        }
+
+        std::fs::read_to_string(Self::mnemonic_path(&self.data_dir))
+            .map(|phrase| phrase.trim().to_string())
+            .map_err(|_| {
+                AppError::Internal(
+                    "no mnemonic on file - this wallet predates mnemonic support".to_string(),
+                )
+            })
+    }
+
+    /// Export a passphrase-protected backup of this wallet's seed material
+    /// (mnemonic, or legacy raw seed if no mnemonic is on file) plus the
+    /// mint it's paired with, so an operator can move it off-box. Derives a
+    /// key from `passphrase` with Argon2id under a fresh salt, then seals
+    /// the serialized backup with ChaCha20-Poly1305 under a fresh nonce.
+    /// Output is `version || salt || nonce || ciphertext+tag`, not encoded
+    /// further since the return type is already raw bytes.
+    pub fn export_encrypted(&self, passphrase: &str) -> AppResult<Vec<u8>> {
+        if self.mock_mode {
+            return Err(AppError::Internal("no wallet seed in mock mode".to_string()));
+        }
+
+        let mnemonic = std::fs::read_to_string(Self::mnemonic_path(&self.data_dir))
+            .ok()
+            .map(|phrase| phrase.trim().to_string());
+
+        let seed_hex = if mnemonic.is_none() {
+            std::fs::read_to_string(Self::seed_path(&self.data_dir))
+                .ok()
+                .map(|hex| hex.trim().to_string())
+        } else {
+            None
+        };
+
+        if mnemonic.is_none() && seed_hex.is_none() {
+            return Err(AppError::Internal("no wallet seed on file".to_string()));
+        }
+
+        let backup = WalletBackup {
+            mnemonic,
+            seed_hex,
+            mint_url: self.mint_url.clone(),
+            unit: self.unit.clone(),
+        };
+
+        let plaintext = serde_json::to_vec(&backup)
+            .map_err(|e| AppError::Internal(format!("Failed to serialize backup: {}", e)))?;
+
+        let mut salt = [0u8; BACKUP_SALT_LEN];
+        rand::thread_rng().fill_bytes(&mut salt);
+
+        let mut key_bytes = [0u8; 32];
+        backup_argon2()
+            .hash_password_into(passphrase.as_bytes(), &salt, &mut key_bytes)
+            .map_err(|e| AppError::Internal(format!("Key derivation failed: {}", e)))?;
+
+        let mut nonce_bytes = [0u8; BACKUP_NONCE_LEN];
+        rand::thread_rng().fill_bytes(&mut nonce_bytes);
+
+        let cipher = ChaCha20Poly1305::new(Key::from_slice(&key_bytes));
+        let ciphertext = cipher
+            .encrypt(Nonce::from_slice(&nonce_bytes), plaintext.as_slice())
+            .map_err(|e| AppError::Internal(format!("Encryption failed: {}", e)))?;
+
+        let mut out = Vec::with_capacity(1 + BACKUP_SALT_LEN + BACKUP_NONCE_LEN + ciphertext.len());
+        out.push(BACKUP_ENC_VERSION);
+        out.extend_from_slice(&salt);
+        out.extend_from_slice(&nonce_bytes);
+        out.extend_from_slice(&ciphertext);
+
+        Ok(out)
+    }
+
+    /// Restore a wallet from an [`Self::export_encrypted`] backup,
+    /// connecting to the same mint configured in `config`. Fails closed: a
+    /// wrong passphrase or a tampered/corrupted bundle is rejected by the
+    /// Poly1305 tag check (surfaced as [`AppError::InvalidWalletBackup`])
+    /// rather than producing garbage seed material.
+    pub async fn import_encrypted(
+        config: &Config,
+        bytes: &[u8],
+        passphrase: &str,
+        db: Database,
+    ) -> AppResult<Self> {
+        if bytes.len() < 1 + BACKUP_SALT_LEN + BACKUP_NONCE_LEN || bytes[0] != BACKUP_ENC_VERSION {
+            return Err(AppError::InvalidWalletBackup);
+        }
+
+        let salt = &bytes[1..1 + BACKUP_SALT_LEN];
+        let nonce_bytes = &bytes[1 + BACKUP_SALT_LEN..1 + BACKUP_SALT_LEN + BACKUP_NONCE_LEN];
+        let ciphertext = &bytes[1 + BACKUP_SALT_LEN + BACKUP_NONCE_LEN..];
+
+        let mut key_bytes = [0u8; 32];
+        backup_argon2()
+            .hash_password_into(passphrase.as_bytes(), salt, &mut key_bytes)
+            .map_err(|e| AppError::Internal(format!("Key derivation failed: {}", e)))?;
+
+        let cipher = ChaCha20Poly1305::new(Key::from_slice(&key_bytes));
+        let plaintext = cipher
+            .decrypt(Nonce::from_slice(nonce_bytes), ciphertext)
+            .map_err(|_| AppError::InvalidWalletBackup)?;
+
+        let backup: WalletBackup =
+            serde_json::from_slice(&plaintext).map_err(|_| AppError::InvalidWalletBackup)?;
+
+        std::fs::create_dir_all(&config.mint.data_dir)
+            .map_err(|e| AppError::Internal(format!("Failed to create data dir: {}", e)))?;
+
+        if let Some(mnemonic) = &backup.mnemonic {
+            std::fs::write(Self::mnemonic_path(&config.mint.data_dir), mnemonic)
+                .map_err(|e| AppError::Internal(format!("Failed to write mnemonic: {}", e)))?;
+        } else if let Some(seed_hex) = &backup.seed_hex {
+            std::fs::write(Self::seed_path(&config.mint.data_dir), seed_hex)
+                .map_err(|e| AppError::Internal(format!("Failed to write seed: {}", e)))?;
+        } else {
+            return Err(AppError::InvalidWalletBackup);
+        }
+
+        Self::new(config, db)
+            .await
+            .map_err(|e| AppError::Internal(format!("Failed to restore wallet: {}", e)))
+    }
+
     // --- Mock mode helpers ---
 
     async fn mock_create_deposit_invoice(&self, amount_sats: u64) -> AppResult<DepositInvoice> {
@@ -318,6 +950,7 @@ impl CashuService {
             payment_hash: quote_id,
             amount_sats,
             expires_at,
+            connector_label: self.mint_url.clone(),
         })
     }
 
@@ -379,9 +1012,29 @@ impl CashuService {
         hex::encode(hash)
     }
 
+    fn seed_path(data_dir: &str) -> String {
+        format!("{}/seed", data_dir)
+    }
+
+    fn mnemonic_path(data_dir: &str) -> String {
+        format!("{}/mnemonic", data_dir)
+    }
+
+    /// Generate or load the wallet's 64-byte CDK seed. Prefers a persisted
+    /// BIP39 mnemonic, deriving the seed via the standard PBKDF2-HMAC-SHA512
+    /// mnemonic-to-seed derivation, so the wallet can be backed up and
+    /// restored by hand ([`Self::export_mnemonic`], [`Self::restore`]).
+    /// Falls back to the legacy raw-hex `seed` file if no mnemonic file
+    /// exists yet, so an existing deployment keeps working unchanged.
     fn get_or_create_seed(data_dir: &str) -> anyhow::Result<[u8; 64]> {
-        let seed_path = format!("{}/seed", data_dir);
+        let mnemonic_path = Self::mnemonic_path(data_dir);
 
+        if let Ok(phrase) = std::fs::read_to_string(&mnemonic_path) {
+            let mnemonic = Mnemonic::parse_in_normalized(Language::English, phrase.trim())?;
+            return Ok(mnemonic.to_seed_normalized(""));
+        }
+
+        let seed_path = Self::seed_path(data_dir);
         if let Ok(seed_hex) = std::fs::read_to_string(&seed_path) {
             let seed_bytes = hex::decode(seed_hex.trim())?;
             if seed_bytes.len() == 64 {
@@ -391,15 +1044,17 @@ impl CashuService {
             }
         }
 
-        // Generate new seed (64 bytes for CDK) using getrandom
-        let mut seed = [0u8; 64];
-        getrandom::getrandom(&mut seed)?;
+        // No existing seed material: generate a fresh 24-word mnemonic
+        // (256 bits of entropy) and persist the phrase instead of the raw
+        // seed, so it can be copied down and used to restore the wallet.
+        let mut entropy = [0u8; 32];
+        getrandom::getrandom(&mut entropy)?;
+        let mnemonic = Mnemonic::from_entropy_in(Language::English, &entropy)?;
 
-        // Save seed
-        std::fs::write(&seed_path, hex::encode(seed))?;
-        tracing::info!("Generated new wallet seed");
+        std::fs::write(&mnemonic_path, mnemonic.to_string())?;
+        tracing::info!("Generated new wallet mnemonic");
 
-        Ok(seed)
+        Ok(mnemonic.to_seed_normalized(""))
     }
 }
 
@@ -410,6 +1065,9 @@ pub struct DepositInvoice {
     pub payment_hash: String,
     pub amount_sats: u64,
     pub expires_at: chrono::DateTime<chrono::Utc>,
+    /// Label of the connector that issued this invoice (its mint URL), so
+    /// the deposit indexer polls the same mint the quote was created on.
+    pub connector_label: String,
 }
 
 /// Withdrawal result
@@ -438,19 +1096,27 @@ pub struct MintInfo {
 mod tests {
     use super::*;
 
-    fn mock_service() -> CashuService {
+    async fn mock_service() -> CashuService {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        db.run_migrations().await.unwrap();
+
         CashuService {
             wallet: None,
             mint_url: "mock".to_string(),
+            data_dir: String::new(),
+            unit: "sat".to_string(),
             pending_quotes: Arc::new(RwLock::new(HashMap::new())),
             mock_mode: true,
             mock_spent_tokens: Arc::new(RwLock::new(HashMap::new())),
+            trusted_mints: Vec::new(),
+            foreign_wallets: Arc::new(RwLock::new(HashMap::new())),
+            db,
         }
     }
 
     #[tokio::test]
     async fn test_mock_deposit_flow() {
-        let service = mock_service();
+        let service = mock_service().await;
 
         // Create invoice
         let invoice = service.create_deposit_invoice(1000).await.unwrap();
@@ -466,22 +1132,54 @@ mod tests {
 
     #[tokio::test]
     async fn test_mock_receive_tokens() {
-        let service = mock_service();
+        let service = mock_service().await;
 
         let token = service.mock_mint_tokens(500).await.unwrap();
-        let amount = service.receive_tokens(&token).await.unwrap();
+        let amount = service.receive_tokens(&token, None).await.unwrap();
         assert_eq!(amount, 500);
 
         // Double spend should fail
-        assert!(service.receive_tokens(&token).await.is_err());
+        assert!(service.receive_tokens(&token, None).await.is_err());
     }
 
     #[tokio::test]
     async fn test_mock_withdraw() {
-        let service = mock_service();
+        let service = mock_service().await;
 
         let result = service.withdraw("lnbc1000n1test", 1000).await.unwrap();
         assert_eq!(result.amount_paid, 1000);
         assert_eq!(result.fee_paid, 0);
     }
+
+    #[tokio::test]
+    async fn test_mock_escrow_lock() {
+        let service = mock_service().await;
+        let (_, buyer) = crate::services::NostrService::generate_keypair().unwrap();
+        let (_, seller) = crate::services::NostrService::generate_keypair().unwrap();
+        let (_, arbiter) = crate::services::NostrService::generate_keypair().unwrap();
+
+        let token = service
+            .create_escrow_lock(1000, &buyer, &seller, &arbiter, chrono::Utc::now())
+            .await
+            .unwrap();
+        assert!(token.starts_with("cashuA"));
+        assert!(token.contains("p2pk_mock"));
+    }
+
+    #[tokio::test]
+    async fn test_token_hash_stable_and_distinct() {
+        let service = mock_service().await;
+
+        let token_a = service.mock_mint_tokens(500).await.unwrap();
+        let token_b = service.mock_mint_tokens(500).await.unwrap();
+
+        assert_eq!(
+            service.token_hash(&token_a).unwrap(),
+            service.token_hash(&token_a).unwrap()
+        );
+        assert_ne!(
+            service.token_hash(&token_a).unwrap(),
+            service.token_hash(&token_b).unwrap()
+        );
+    }
 }
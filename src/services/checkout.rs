@@ -0,0 +1,37 @@
+use crate::db::Database;
+use crate::error::AppResult;
+
+/// Checkout session housekeeping
+pub struct CheckoutService;
+
+impl CheckoutService {
+    /// Expire `pending` checkout sessions whose price lock (`expires_at`)
+    /// has passed, releasing the listings they held. Marks each session
+    /// `expired` and deletes its `checkout_items` in one transaction, so a
+    /// crash mid-sweep can't leave a session half-released.
+    pub async fn expire_pending(db: &Database) -> AppResult<u32> {
+        let mut db_tx = db.pool().begin().await?;
+
+        let expired: Vec<(String,)> = sqlx::query_as(
+            "SELECT id FROM checkout_sessions WHERE status = 'pending' AND expires_at <= CURRENT_TIMESTAMP",
+        )
+        .fetch_all(&mut *db_tx)
+        .await?;
+
+        for (checkout_id,) in &expired {
+            sqlx::query("DELETE FROM checkout_items WHERE checkout_id = ?")
+                .bind(checkout_id)
+                .execute(&mut *db_tx)
+                .await?;
+
+            sqlx::query("UPDATE checkout_sessions SET status = 'expired' WHERE id = ?")
+                .bind(checkout_id)
+                .execute(&mut *db_tx)
+                .await?;
+        }
+
+        db_tx.commit().await?;
+
+        Ok(expired.len() as u32)
+    }
+}
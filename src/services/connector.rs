@@ -0,0 +1,253 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use crate::error::{AppError, AppResult};
+use crate::services::cashu::{CashuService, DepositInvoice, MintInfo, WithdrawalResult};
+
+/// A payment backend capable of issuing invoices, paying them, and
+/// receiving bearer ecash tokens. `CashuService` (one instance per
+/// configured mint) is the only implementation today; the trait is the
+/// seam a direct Lightning node backend (LND/CLN) would plug into without
+/// [`ConnectorRouter`] or its callers changing.
+#[async_trait]
+pub trait PaymentConnector: Send + Sync {
+    /// Short label identifying this connector in logs and in
+    /// [`crate::services::DepositIndexer`] tracking (currently the mint URL).
+    fn label(&self) -> String;
+
+    async fn create_invoice(&self, amount_sats: u64) -> AppResult<DepositInvoice>;
+
+    async fn pay_invoice(&self, invoice: &str, amount_sats: u64) -> AppResult<WithdrawalResult>;
+
+    /// `credit_npub` names the user this redemption is for, if any — see
+    /// [`CashuService::receive_tokens`].
+    async fn receive_token(&self, token_str: &str, credit_npub: Option<&str>) -> AppResult<u64>;
+
+    fn mint_info(&self) -> MintInfo;
+
+    /// Cheap liveness check the router uses to skip a known-bad connector
+    /// before spending a round trip on it.
+    async fn health(&self) -> bool;
+}
+
+#[async_trait]
+impl PaymentConnector for CashuService {
+    fn label(&self) -> String {
+        self.mint_info().url
+    }
+
+    async fn create_invoice(&self, amount_sats: u64) -> AppResult<DepositInvoice> {
+        self.create_deposit_invoice(amount_sats).await
+    }
+
+    async fn pay_invoice(&self, invoice: &str, amount_sats: u64) -> AppResult<WithdrawalResult> {
+        self.withdraw(invoice, amount_sats).await
+    }
+
+    async fn receive_token(&self, token_str: &str, credit_npub: Option<&str>) -> AppResult<u64> {
+        self.receive_tokens(token_str, credit_npub).await
+    }
+
+    fn mint_info(&self) -> MintInfo {
+        CashuService::mint_info(self)
+    }
+
+    async fn health(&self) -> bool {
+        self.is_mock_mode() || self.get_balance().await.is_ok()
+    }
+}
+
+#[async_trait]
+impl<T: PaymentConnector + ?Sized> PaymentConnector for Arc<T> {
+    fn label(&self) -> String {
+        (**self).label()
+    }
+
+    async fn create_invoice(&self, amount_sats: u64) -> AppResult<DepositInvoice> {
+        (**self).create_invoice(amount_sats).await
+    }
+
+    async fn pay_invoice(&self, invoice: &str, amount_sats: u64) -> AppResult<WithdrawalResult> {
+        (**self).pay_invoice(invoice, amount_sats).await
+    }
+
+    async fn receive_token(&self, token_str: &str, credit_npub: Option<&str>) -> AppResult<u64> {
+        (**self).receive_token(token_str, credit_npub).await
+    }
+
+    fn mint_info(&self) -> MintInfo {
+        (**self).mint_info()
+    }
+
+    async fn health(&self) -> bool {
+        (**self).health().await
+    }
+}
+
+/// Routes payment operations across an ordered list of connectors —
+/// typically one `CashuService` per operator-configured mint, in priority
+/// order. `create_invoice` and `pay_invoice` skip connectors that fail a
+/// health check and fall through to the next on failure, logging the
+/// connector they picked (or rejected) like a payment router does;
+/// `receive_token` tries each connector in order without a health check.
+/// The quote-based withdrawal operations (`quote_withdrawal`,
+/// `execute_withdrawal`, `check_withdrawal_paid`) always use the primary
+/// mint, with no fallback to the others.
+pub struct ConnectorRouter {
+    connectors: Vec<Box<dyn PaymentConnector>>,
+    mints: Vec<Arc<CashuService>>,
+}
+
+impl ConnectorRouter {
+    pub fn new(mints: Vec<Arc<CashuService>>) -> AppResult<Self> {
+        if mints.is_empty() {
+            return Err(AppError::Internal(
+                "at least one payment connector must be configured".to_string(),
+            ));
+        }
+
+        let connectors = mints
+            .iter()
+            .map(|m| Box::new(Arc::clone(m)) as Box<dyn PaymentConnector>)
+            .collect();
+
+        Ok(Self { connectors, mints })
+    }
+
+    /// The primary (first-configured) mint, for Cashu-specific operations
+    /// (ecash export, P2PK escrow locks, replay-safe token hashing) that
+    /// have no equivalent on a non-Cashu backend and so aren't part of
+    /// [`PaymentConnector`].
+    pub fn primary_mint(&self) -> &CashuService {
+        &self.mints[0]
+    }
+
+    /// Look up a specific mint by the label it issued a deposit under, so
+    /// the deposit indexer can poll the mint that actually holds the quote
+    /// rather than assuming it was always the primary.
+    pub fn mint_by_label(&self, label: &str) -> Option<&CashuService> {
+        self.mints.iter().map(Arc::as_ref).find(|m| m.label() == label)
+    }
+
+    pub fn mint_info(&self) -> MintInfo {
+        self.primary_mint().mint_info()
+    }
+
+    pub fn is_mock_mode(&self) -> bool {
+        self.primary_mint().is_mock_mode()
+    }
+
+    pub async fn create_invoice(&self, amount_sats: u64) -> AppResult<DepositInvoice> {
+        let mut last_err = None;
+        for connector in &self.connectors {
+            if !connector.health().await {
+                tracing::warn!("skipping unhealthy connector {}", connector.label());
+                continue;
+            }
+            match connector.create_invoice(amount_sats).await {
+                Ok(invoice) => {
+                    tracing::debug!("routed deposit invoice to connector {}", connector.label());
+                    return Ok(invoice);
+                }
+                Err(e) => {
+                    tracing::warn!(
+                        "connector {} failed to create invoice: {}",
+                        connector.label(),
+                        e
+                    );
+                    last_err = Some(e);
+                }
+            }
+        }
+        Err(last_err.unwrap_or_else(Self::no_connectors_err))
+    }
+
+    pub async fn pay_invoice(&self, invoice: &str, amount_sats: u64) -> AppResult<WithdrawalResult> {
+        let mut last_err = None;
+        for connector in &self.connectors {
+            if !connector.health().await {
+                tracing::warn!("skipping unhealthy connector {}", connector.label());
+                continue;
+            }
+            match connector.pay_invoice(invoice, amount_sats).await {
+                Ok(result) => {
+                    tracing::debug!("routed withdrawal through connector {}", connector.label());
+                    return Ok(result);
+                }
+                Err(e) => {
+                    tracing::warn!(
+                        "connector {} failed to pay invoice: {}",
+                        connector.label(),
+                        e
+                    );
+                    last_err = Some(e);
+                }
+            }
+        }
+        Err(last_err.unwrap_or_else(Self::no_connectors_err))
+    }
+
+    pub async fn receive_token(&self, token_str: &str, credit_npub: Option<&str>) -> AppResult<u64> {
+        let mut last_err = None;
+        for connector in &self.connectors {
+            match connector.receive_token(token_str, credit_npub).await {
+                Ok(amount) => return Ok(amount),
+                Err(e) => {
+                    tracing::warn!(
+                        "connector {} failed to receive token: {}",
+                        connector.label(),
+                        e
+                    );
+                    last_err = Some(e);
+                }
+            }
+        }
+        Err(last_err.unwrap_or_else(Self::no_connectors_err))
+    }
+
+    /// Quote a withdrawal against the primary mint without paying it yet,
+    /// returning its quote id and fee reserve, so a caller that needs
+    /// crash-safety (see
+    /// [`ReconciliationService::withdraw`](crate::services::ReconciliationService::withdraw))
+    /// can persist the quote id before attempting payment and check its
+    /// actual outcome later instead of blindly retrying on any `Err`.
+    pub async fn quote_withdrawal(&self, invoice: &str) -> AppResult<(String, u64)> {
+        self.primary_mint().create_melt_quote(invoice).await
+    }
+
+    /// Pay a quote previously created with [`Self::quote_withdrawal`].
+    pub async fn execute_withdrawal(
+        &self,
+        quote_id: &str,
+        amount_sats: u64,
+        fee_reserve: u64,
+    ) -> AppResult<WithdrawalResult> {
+        self.primary_mint()
+            .execute_melt_quote(quote_id, amount_sats, fee_reserve)
+            .await
+    }
+
+    /// Check whether a previously-quoted withdrawal actually paid despite
+    /// an `Err` from [`Self::execute_withdrawal`] — see
+    /// [`CashuService::check_melt_paid`].
+    pub async fn check_withdrawal_paid(&self, quote_id: &str) -> AppResult<bool> {
+        self.primary_mint().check_melt_paid(quote_id).await
+    }
+
+    /// Recover any melt->mint bridge stuck between a foreign melt and its
+    /// home mint call (see [`CashuService::sweep_pending_bridge_mints`])
+    /// on every configured mint, for the periodic reconciliation task.
+    /// Returns how many bridges were recovered in total.
+    pub async fn sweep_pending_bridge_mints(&self) -> AppResult<u32> {
+        let mut recovered = 0;
+        for mint in &self.mints {
+            recovered += mint.sweep_pending_bridge_mints().await?;
+        }
+        Ok(recovered)
+    }
+
+    fn no_connectors_err() -> AppError {
+        AppError::Internal("no payment connectors configured".to_string())
+    }
+}
@@ -0,0 +1,163 @@
+use tokio::sync::RwLock;
+
+use crate::bloom::BloomFilter;
+use crate::db::Database;
+use crate::error::AppResult;
+use crate::services::{ConnectorRouter, LedgerService, ACCOUNT_MINT_FLOAT};
+
+/// Bit width of the bloom filter backing [`DepositIndexer`]. Sized generously
+/// for a single marketplace instance's outstanding deposit identifiers.
+const BLOOM_BITS: usize = 1 << 16;
+const BLOOM_HASHES: u32 = 4;
+
+/// Watches for confirmed Cashu mint-quote deposits and credits the right
+/// user's wallet balance, replacing the old manual, ad hoc crediting path
+/// where only direct token deposits (not Lightning invoices) were ever
+/// recorded.
+pub struct DepositIndexer {
+    filter: RwLock<BloomFilter>,
+}
+
+impl DepositIndexer {
+    /// Build an indexer and seed its bloom filter from every deposit this
+    /// marketplace has not yet credited, so a restart doesn't need to
+    /// rescan everything to avoid false negatives.
+    pub async fn new(db: &Database) -> AppResult<Self> {
+        let mut filter = BloomFilter::new(BLOOM_BITS, BLOOM_HASHES);
+
+        let uncredited: Vec<(String,)> =
+            sqlx::query_as("SELECT identifier FROM tracked_deposits WHERE credited = FALSE")
+                .fetch_all(db.pool())
+                .await?;
+        for (identifier,) in uncredited {
+            filter.insert(&identifier);
+        }
+
+        Ok(Self {
+            filter: RwLock::new(filter),
+        })
+    }
+
+    /// Register a freshly issued deposit identifier (mint quote id) so the
+    /// next scan watches for it. `connector_label` records which connector
+    /// (mint) issued it, so a multi-mint setup polls the right one.
+    pub async fn track(
+        &self,
+        db: &Database,
+        identifier: &str,
+        user_npub: &str,
+        amount_sats: u64,
+        connector_label: &str,
+    ) -> AppResult<()> {
+        self.filter.write().await.insert(identifier);
+
+        sqlx::query(
+            "INSERT INTO tracked_deposits (identifier, user_npub, amount_sats, connector_label, credited, created_at) VALUES (?, ?, ?, ?, FALSE, CURRENT_TIMESTAMP)",
+        )
+        .bind(identifier)
+        .bind(user_npub)
+        .bind(amount_sats as i64)
+        .bind(connector_label)
+        .execute(db.pool())
+        .await?;
+
+        Ok(())
+    }
+
+    /// Scan every outstanding deposit identifier once, crediting any that
+    /// have been paid. Walks the whole uncredited set per pass rather than
+    /// stopping at the first match, so a batch of deposits landing between
+    /// scans all get credited in one go.
+    pub async fn scan_once(&self, db: &Database, cashu: &ConnectorRouter) -> AppResult<u32> {
+        let pending: Vec<(String, String, i64, String)> = sqlx::query_as(
+            "SELECT identifier, user_npub, amount_sats, connector_label FROM tracked_deposits WHERE credited = FALSE",
+        )
+        .fetch_all(db.pool())
+        .await?;
+
+        let mut credited = 0u32;
+        for (identifier, user_npub, amount_sats, connector_label) in pending {
+            if !self.filter.read().await.might_contain(&identifier) {
+                continue;
+            }
+
+            let Some(mint) = cashu.mint_by_label(&connector_label) else {
+                tracing::warn!(
+                    "tracked deposit {} references unknown connector {}, skipping",
+                    identifier,
+                    connector_label
+                );
+                continue;
+            };
+
+            if !mint.check_invoice_paid(&identifier).await? {
+                continue;
+            }
+
+            let token = mint.mint_tokens(&identifier, amount_sats as u64).await?;
+            let amount = mint.receive_tokens(&token, Some(&user_npub)).await?;
+
+            if Self::credit_once(db, &user_npub, amount as i64, &identifier).await? {
+                credited += 1;
+            }
+        }
+
+        Self::save_cursor(db).await?;
+        Ok(credited)
+    }
+
+    /// Credit `user_npub` and mark `identifier` credited inside one
+    /// transaction, guarded by a unique index on
+    /// `wallet_transactions.external_deposit_id` so a replayed scan (e.g.
+    /// after a crash between the mint call and the DB write) can't credit
+    /// the same deposit twice. Returns `false` if it was already credited.
+    async fn credit_once(
+        db: &Database,
+        user_npub: &str,
+        amount: i64,
+        identifier: &str,
+    ) -> AppResult<bool> {
+        let mut tx = db.pool().begin().await?;
+
+        LedgerService::post(&mut tx, ACCOUNT_MINT_FLOAT, user_npub, amount, "deposit", Some(identifier))
+            .await?;
+
+        let (new_balance,): (i64,) =
+            sqlx::query_as("SELECT wallet_balance FROM users WHERE npub = ?")
+                .bind(user_npub)
+                .fetch_one(&mut *tx)
+                .await?;
+
+        let tx_id = uuid::Uuid::new_v4().to_string();
+        let inserted = sqlx::query(
+            "INSERT OR IGNORE INTO wallet_transactions (id, user_npub, transaction_type, amount, balance_after, description, external_deposit_id, created_at) VALUES (?, ?, 'deposit', ?, ?, 'Lightning deposit (indexer)', ?, CURRENT_TIMESTAMP)",
+        )
+        .bind(&tx_id)
+        .bind(user_npub)
+        .bind(amount)
+        .bind(new_balance)
+        .bind(identifier)
+        .execute(&mut *tx)
+        .await?;
+
+        if inserted.rows_affected() == 0 {
+            tx.rollback().await?;
+            return Ok(false);
+        }
+
+        sqlx::query("UPDATE tracked_deposits SET credited = TRUE WHERE identifier = ?")
+            .bind(identifier)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    async fn save_cursor(db: &Database) -> AppResult<()> {
+        sqlx::query("UPDATE deposit_indexer_cursor SET last_scanned_at = CURRENT_TIMESTAMP WHERE id = 1")
+            .execute(db.pool())
+            .await?;
+        Ok(())
+    }
+}
@@ -1,16 +1,44 @@
 use chrono::{Duration, Utc};
+use sqlx::{Sqlite, Transaction};
 
 use crate::db::Database;
 use crate::error::{AppError, AppResult};
-use crate::models::{DisputeResolution, Escrow, EscrowStatus, TransactionType};
+use crate::models::{
+    Dispute, DisputeResolution, Escrow, EscrowMessageKind, EscrowPlan, EscrowStatus, Payee,
+    TransactionType, Witness,
+};
+use crate::services::{
+    escrow_hold_account, CashuService, EscrowCoordinator, EscrowEventBus, EscrowEventService,
+    LedgerService, ACCOUNT_BURNED, ACCOUNT_MINT_FLOAT,
+};
 
 /// Escrow management service
 pub struct EscrowService;
 
 impl EscrowService {
     /// Create a new escrow for an order
+    ///
+    /// In mock mode, also locks `amount` sats into a NUT-11 P2PK token
+    /// co-owned by `buyer_npub`/`seller_npub`/`coordinator.arbiter_npub()`
+    /// (see [`CashuService::create_escrow_lock`]) in addition to recording
+    /// the hold against the buyer's internal wallet balance. Against a real
+    /// mint this step is skipped — there's no release-plan execution yet
+    /// that could ever redeem such a token (see the long comment on
+    /// `create_escrow_lock`), so `locked_proofs` stays unset and the escrow
+    /// is pure internal-balance bookkeeping, same as before P2PK locking
+    /// existed. Once the escrow is created, queues a DM notifying the
+    /// seller of the handshake (see [`EscrowCoordinator::notify`]).
+    ///
+    /// Takes `db_tx` rather than a `&Database` so a caller that creates
+    /// several escrows alongside other writes (e.g. checkout, which also
+    /// deducts the buyer's balance and inserts orders) can fold all of it
+    /// into one transaction instead of committing per escrow. The caller
+    /// owns the commit and should only call `events.notify_all()` after it
+    /// succeeds.
     pub async fn create_escrow(
-        db: &Database,
+        db_tx: &mut Transaction<'_, Sqlite>,
+        cashu: &CashuService,
+        coordinator: &EscrowCoordinator,
         buyer_npub: &str,
         seller_npub: &str,
         amount: i64,
@@ -18,11 +46,38 @@ impl EscrowService {
     ) -> AppResult<Escrow> {
         let id = uuid::Uuid::new_v4().to_string();
         let auto_release_at = Utc::now() + Duration::days(escrow_days as i64);
+        let arbiter_npub = coordinator.arbiter_npub()?;
+
+        let locked_proofs = if cashu.is_mock_mode() {
+            Some(
+                cashu
+                    .create_escrow_lock(
+                        amount as u64,
+                        buyer_npub,
+                        seller_npub,
+                        &arbiter_npub,
+                        auto_release_at,
+                    )
+                    .await?,
+            )
+        } else {
+            tracing::warn!(
+                "escrow {} created against a real mint without a P2PK lock: non-custodial settlement isn't implemented yet, falling back to internal bookkeeping",
+                id
+            );
+            None
+        };
+
+        // Buyer confirmation or the auto-release timelock pays the seller;
+        // admin arbitration refunds the buyer. See `EscrowPlan::apply_witness`.
+        let plan = EscrowPlan::purchase_plan(amount, buyer_npub, &arbiter_npub, auto_release_at);
+        let plan_json = serde_json::to_string(&plan)
+            .map_err(|e| AppError::Internal(format!("Failed to serialize escrow plan: {}", e)))?;
 
         sqlx::query(
             r#"
-            INSERT INTO escrows (id, buyer_npub, seller_npub, amount, status, auto_release_at, created_at)
-            VALUES (?, ?, ?, ?, 'held', ?, CURRENT_TIMESTAMP)
+            INSERT INTO escrows (id, buyer_npub, seller_npub, amount, status, auto_release_at, created_at, arbiter_npub, locked_proofs, plan)
+            VALUES (?, ?, ?, ?, 'held', ?, CURRENT_TIMESTAMP, ?, ?, ?)
             "#,
         )
         .bind(&id)
@@ -30,37 +85,140 @@ impl EscrowService {
         .bind(seller_npub)
         .bind(amount)
         .bind(auto_release_at)
-        .execute(db.pool())
+        .bind(&arbiter_npub)
+        .bind(&locked_proofs)
+        .bind(&plan_json)
+        .execute(&mut **db_tx)
         .await?;
 
-        // Deduct from buyer's wallet and log transaction
-        Self::deduct_wallet(db, buyer_npub, amount, TransactionType::EscrowHold, Some(&id)).await?;
+        // Deduct from buyer's wallet and log transaction, in the same
+        // transaction as the escrow row so a crash can't leave one
+        // without the other.
+        Self::deduct_wallet(db_tx, buyer_npub, amount, TransactionType::EscrowHold, Some(&id)).await?;
+
+        coordinator
+            .notify(
+                db_tx,
+                &id,
+                seller_npub,
+                EscrowMessageKind::Lock,
+                &serde_json::json!({
+                    "escrow_id": id,
+                    "amount": amount,
+                    "locked_token": locked_proofs,
+                    "auto_release_at": auto_release_at.to_rfc3339(),
+                }),
+            )
+            .await?;
+
+        EscrowEventService::record(db_tx, &id, "none", "held", amount).await?;
 
         let escrow = sqlx::query_as::<_, Escrow>("SELECT * FROM escrows WHERE id = ?")
             .bind(&id)
-            .fetch_one(db.pool())
+            .fetch_one(&mut **db_tx)
             .await?;
 
         Ok(escrow)
     }
 
-    /// Release escrow funds to seller (buyer confirms or auto-release)
-    pub async fn release_escrow(db: &Database, escrow_id: &str) -> AppResult<()> {
+    /// Apply a witness to an escrow's stored [`EscrowPlan`] and persist the
+    /// reduced plan. Returns the terminal payout if the plan fully
+    /// resolved, or `None` if `witness` didn't satisfy anything yet.
+    /// Escrows created before the `plan` column existed have no plan to
+    /// reduce and are left untouched.
+    async fn reduce_plan(
+        db_tx: &mut Transaction<'_, Sqlite>,
+        escrow: &Escrow,
+        witness: Witness,
+    ) -> AppResult<Option<(i64, Payee)>> {
+        let Some(plan_json) = &escrow.plan else {
+            return Ok(None);
+        };
+
+        let plan: EscrowPlan = serde_json::from_str(plan_json)
+            .map_err(|e| AppError::Internal(format!("Failed to parse escrow plan: {}", e)))?;
+        let reduced = plan.apply_witness(&witness);
+
+        let reduced_json = serde_json::to_string(&reduced)
+            .map_err(|e| AppError::Internal(format!("Failed to serialize escrow plan: {}", e)))?;
+        sqlx::query("UPDATE escrows SET plan = ? WHERE id = ?")
+            .bind(&reduced_json)
+            .bind(&escrow.id)
+            .execute(&mut **db_tx)
+            .await?;
+
+        Ok(reduced.as_payment())
+    }
+
+    /// Buyer confirms receipt: feeds [`Witness::Signed(buyer_npub)`] into
+    /// the escrow's plan, which satisfies the `Or` branch that pays the
+    /// seller immediately instead of waiting for the auto-release timelock.
+    pub async fn confirm_receipt(
+        db: &Database,
+        coordinator: &EscrowCoordinator,
+        events: &EscrowEventBus,
+        escrow_id: &str,
+        buyer_npub: &str,
+    ) -> AppResult<()> {
+        Self::release_escrow(
+            db,
+            coordinator,
+            events,
+            escrow_id,
+            Witness::Signed(buyer_npub.to_string()),
+        )
+        .await
+    }
+
+    /// Release escrow funds to seller (buyer confirms or auto-release
+    /// timelock). `witness` must satisfy the plan's seller-payout branch —
+    /// [`Witness::Signed`] with the buyer's npub, or [`Witness::Now`] past
+    /// `auto_release_at`. Queues a DM handing the seller this witness so
+    /// they can assemble their half of the 2-of-3 signature over the
+    /// locked token alongside the arbiter's.
+    pub async fn release_escrow(
+        db: &Database,
+        coordinator: &EscrowCoordinator,
+        events: &EscrowEventBus,
+        escrow_id: &str,
+        witness: Witness,
+    ) -> AppResult<()> {
         let escrow = Self::get_escrow(db, escrow_id).await?;
 
-        if escrow.status_enum() != EscrowStatus::Held {
-            return Err(AppError::EscrowAlreadyReleased);
+        EscrowStatus::assert_transition(escrow.status_enum(), EscrowStatus::Released)?;
+
+        let mut db_tx = db.pool().begin().await?;
+
+        if let Some((_, payee)) = Self::reduce_plan(&mut db_tx, &escrow, witness.clone()).await? {
+            if payee != Payee::Seller {
+                return Err(AppError::NotAuthorized);
+            }
         }
 
-        // Update escrow status
-        sqlx::query("UPDATE escrows SET status = 'released', resolved_at = CURRENT_TIMESTAMP WHERE id = ?")
-            .bind(escrow_id)
-            .execute(db.pool())
-            .await?;
+        // Update escrow status. Guarded by the status this call read
+        // `assert_transition` against — if a concurrent call already moved
+        // the escrow on, this affects zero rows instead of silently
+        // double-releasing (`assert_transition` alone only checks a
+        // pre-transaction read, which a second concurrent caller can pass
+        // just as easily as the first).
+        let result = sqlx::query(
+            "UPDATE escrows SET status = 'released', resolved_at = CURRENT_TIMESTAMP WHERE id = ? AND status = ?",
+        )
+        .bind(escrow_id)
+        .bind(&escrow.status)
+        .execute(&mut *db_tx)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::InvalidEscrowTransition {
+                from: escrow.status.clone(),
+                to: EscrowStatus::Released.into(),
+            });
+        }
 
         // Credit seller's wallet
         Self::credit_wallet(
-            db,
+            &mut db_tx,
             &escrow.seller_npub,
             escrow.amount,
             TransactionType::EscrowRelease,
@@ -71,32 +229,76 @@ impl EscrowService {
         // Update related order status
         sqlx::query("UPDATE orders SET status = 'completed', completed_at = CURRENT_TIMESTAMP WHERE escrow_id = ?")
             .bind(escrow_id)
-            .execute(db.pool())
+            .execute(&mut *db_tx)
             .await?;
 
+        coordinator
+            .notify(
+                &mut db_tx,
+                escrow_id,
+                &escrow.seller_npub,
+                EscrowMessageKind::ReleaseWitness,
+                &serde_json::json!({ "escrow_id": escrow_id, "witness": witness }),
+            )
+            .await?;
+
+        EscrowEventService::record(&mut db_tx, escrow_id, "held", "released", escrow.amount)
+            .await?;
+
+        LedgerService::assert_escrow_conserved(&mut db_tx, escrow_id).await?;
+
+        db_tx.commit().await?;
+        events.notify_all();
+
         Ok(())
     }
 
-    /// Refund escrow funds to buyer
+    /// Refund escrow funds to buyer. `witness` must satisfy the plan's
+    /// buyer-refund branch — [`Witness::Signed`] with the arbiter's npub.
+    /// Queues a DM handing the buyer this witness so they can assemble
+    /// their half of the 2-of-3 signature over the locked token.
     #[allow(dead_code)]
-    pub async fn refund_escrow(db: &Database, escrow_id: &str) -> AppResult<()> {
+    pub async fn refund_escrow(
+        db: &Database,
+        coordinator: &EscrowCoordinator,
+        events: &EscrowEventBus,
+        escrow_id: &str,
+        witness: Witness,
+    ) -> AppResult<()> {
         let escrow = Self::get_escrow(db, escrow_id).await?;
 
-        if escrow.status_enum() != EscrowStatus::Held
-            && escrow.status_enum() != EscrowStatus::Disputed
-        {
-            return Err(AppError::EscrowAlreadyRefunded);
+        EscrowStatus::assert_transition(escrow.status_enum(), EscrowStatus::Refunded)?;
+
+        let old_status = escrow.status.clone();
+
+        let mut db_tx = db.pool().begin().await?;
+
+        if let Some((_, payee)) = Self::reduce_plan(&mut db_tx, &escrow, witness.clone()).await? {
+            if payee != Payee::Buyer {
+                return Err(AppError::NotAuthorized);
+            }
         }
 
-        // Update escrow status
-        sqlx::query("UPDATE escrows SET status = 'refunded', resolved_at = CURRENT_TIMESTAMP WHERE id = ?")
-            .bind(escrow_id)
-            .execute(db.pool())
-            .await?;
+        // Update escrow status, guarded against a concurrent resolution —
+        // see the matching comment in `release_escrow`.
+        let result = sqlx::query(
+            "UPDATE escrows SET status = 'refunded', resolved_at = CURRENT_TIMESTAMP WHERE id = ? AND status = ?",
+        )
+        .bind(escrow_id)
+        .bind(&old_status)
+        .execute(&mut *db_tx)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::InvalidEscrowTransition {
+                from: old_status,
+                to: EscrowStatus::Refunded.into(),
+            });
+        }
 
         // Credit buyer's wallet
         Self::credit_wallet(
-            db,
+            &mut db_tx,
             &escrow.buyer_npub,
             escrow.amount,
             TransactionType::EscrowRefund,
@@ -107,61 +309,149 @@ impl EscrowService {
         // Update related order status
         sqlx::query("UPDATE orders SET status = 'refunded' WHERE escrow_id = ?")
             .bind(escrow_id)
-            .execute(db.pool())
+            .execute(&mut *db_tx)
+            .await?;
+
+        coordinator
+            .notify(
+                &mut db_tx,
+                escrow_id,
+                &escrow.buyer_npub,
+                EscrowMessageKind::RefundWitness,
+                &serde_json::json!({ "escrow_id": escrow_id, "witness": witness }),
+            )
+            .await?;
+
+        EscrowEventService::record(&mut db_tx, escrow_id, &old_status, "refunded", escrow.amount)
             .await?;
 
+        LedgerService::assert_escrow_conserved(&mut db_tx, escrow_id).await?;
+
+        db_tx.commit().await?;
+        events.notify_all();
+
         Ok(())
     }
 
     /// Resolve dispute with specified resolution
+    ///
+    /// Admin arbitration feeds [`Witness::Signed(admin_npub)`] into the
+    /// escrow's plan so it's recorded alongside the other ways a plan can
+    /// resolve. Only `BuyerFull` maps onto the plan's admin-refund branch;
+    /// `SellerFull`/`Split`/`Burn` are richer outcomes the plan doesn't
+    /// model and are applied directly via `wallet_transactions` instead.
+    ///
+    /// Whatever `resolution.calculate_amounts` doesn't pay out to either
+    /// party (all of it for `Burn`, a rounding remainder for `Split`) is
+    /// posted to [`ACCOUNT_BURNED`] so the hold account still nets to zero —
+    /// see [`LedgerService::assert_escrow_conserved`], asserted before
+    /// commit so an unaccounted-for remainder aborts the whole resolution
+    /// instead of silently vanishing.
     pub async fn resolve_dispute(
         db: &Database,
+        coordinator: &EscrowCoordinator,
+        events: &EscrowEventBus,
         escrow_id: &str,
+        admin_npub: &str,
         resolution: DisputeResolution,
     ) -> AppResult<()> {
         let escrow = Self::get_escrow(db, escrow_id).await?;
 
-        if escrow.status_enum() != EscrowStatus::Disputed {
-            return Err(AppError::EscrowNotFound);
-        }
-
-        let (buyer_amount, seller_amount) = resolution.calculate_amounts(escrow.amount);
-
         // Update escrow status based on resolution
         let new_status = match resolution {
-            DisputeResolution::BuyerFull => "refunded",
-            DisputeResolution::SellerFull => "released",
-            DisputeResolution::Split { .. } => "released", // partial release
-            DisputeResolution::Burn => "released",         // funds burned
+            DisputeResolution::BuyerFull => EscrowStatus::Refunded,
+            DisputeResolution::SellerFull => EscrowStatus::Released,
+            DisputeResolution::Split { .. } => EscrowStatus::Released, // partial release
+            DisputeResolution::Burn => EscrowStatus::Released,         // funds burned
         };
 
-        sqlx::query("UPDATE escrows SET status = ?, resolved_at = CURRENT_TIMESTAMP WHERE id = ?")
-            .bind(new_status)
-            .bind(escrow_id)
-            .execute(db.pool())
-            .await?;
+        EscrowStatus::assert_transition(escrow.status_enum(), new_status)?;
+        let new_status: String = new_status.into();
+
+        let mut db_tx = db.pool().begin().await?;
+
+        let witness = Witness::Signed(admin_npub.to_string());
+        Self::reduce_plan(&mut db_tx, &escrow, witness.clone()).await?;
+
+        let (buyer_amount, seller_amount) = resolution.calculate_amounts(escrow.amount);
+
+        // Guarded against a concurrent resolution — see the matching
+        // comment in `release_escrow`.
+        let result = sqlx::query(
+            "UPDATE escrows SET status = ?, resolved_at = CURRENT_TIMESTAMP WHERE id = ? AND status = ?",
+        )
+        .bind(&new_status)
+        .bind(escrow_id)
+        .bind(&escrow.status)
+        .execute(&mut *db_tx)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::InvalidEscrowTransition {
+                from: escrow.status.clone(),
+                to: new_status,
+            });
+        }
 
         // Distribute funds
         if buyer_amount > 0 {
             Self::credit_wallet(
-                db,
+                &mut db_tx,
                 &escrow.buyer_npub,
                 buyer_amount,
                 TransactionType::EscrowRefund,
                 Some(escrow_id),
             )
             .await?;
+
+            coordinator
+                .notify(
+                    &mut db_tx,
+                    escrow_id,
+                    &escrow.buyer_npub,
+                    EscrowMessageKind::RefundWitness,
+                    &serde_json::json!({ "escrow_id": escrow_id, "amount": buyer_amount, "witness": witness }),
+                )
+                .await?;
         }
 
         if seller_amount > 0 {
             Self::credit_wallet(
-                db,
+                &mut db_tx,
                 &escrow.seller_npub,
                 seller_amount,
                 TransactionType::EscrowRelease,
                 Some(escrow_id),
             )
             .await?;
+
+            coordinator
+                .notify(
+                    &mut db_tx,
+                    escrow_id,
+                    &escrow.seller_npub,
+                    EscrowMessageKind::ReleaseWitness,
+                    &serde_json::json!({ "escrow_id": escrow_id, "amount": seller_amount, "witness": witness }),
+                )
+                .await?;
+        }
+
+        // Whatever `buyer_amount`/`seller_amount` didn't account for —
+        // the whole amount for `Burn`, or a `Split` rounding remainder —
+        // still has to leave the hold account somewhere, or it's a sat
+        // the ledger tracks forever without ever saying where it went.
+        let burned_amount = escrow.amount - buyer_amount - seller_amount;
+        if burned_amount > 0 {
+            let hold_account = escrow_hold_account(escrow_id);
+            LedgerService::post(
+                &mut db_tx,
+                &hold_account,
+                ACCOUNT_BURNED,
+                burned_amount,
+                &String::from(TransactionType::EscrowBurn),
+                Some(escrow_id),
+            )
+            .await?;
         }
 
         // Update order status
@@ -172,18 +462,69 @@ impl EscrowService {
         sqlx::query("UPDATE orders SET status = ?, completed_at = CURRENT_TIMESTAMP WHERE escrow_id = ?")
             .bind(order_status)
             .bind(escrow_id)
-            .execute(db.pool())
+            .execute(&mut *db_tx)
             .await?;
 
+        EscrowEventService::record(&mut db_tx, escrow_id, "disputed", &new_status, escrow.amount)
+            .await?;
+
+        LedgerService::assert_escrow_conserved(&mut db_tx, escrow_id).await?;
+
+        db_tx.commit().await?;
+        events.notify_all();
+
         Ok(())
     }
 
-    /// Mark escrow as disputed
-    pub async fn mark_disputed(db: &Database, escrow_id: &str) -> AppResult<()> {
-        sqlx::query("UPDATE escrows SET status = 'disputed' WHERE id = ? AND status = 'held'")
+    /// Buyer opens a dispute: holds the escrow (no plan witness satisfies
+    /// anything by itself) until an admin arbitrates via
+    /// [`Self::resolve_dispute`]. Notifies both parties so the seller isn't
+    /// left guessing why the handshake stalled.
+    pub async fn dispute(
+        db: &Database,
+        coordinator: &EscrowCoordinator,
+        events: &EscrowEventBus,
+        escrow_id: &str,
+    ) -> AppResult<()> {
+        let escrow = Self::get_escrow(db, escrow_id).await?;
+
+        EscrowStatus::assert_transition(escrow.status_enum(), EscrowStatus::Disputed)?;
+
+        let mut db_tx = db.pool().begin().await?;
+
+        // Guarded against a concurrent resolution — see the matching
+        // comment in `release_escrow`.
+        let result = sqlx::query("UPDATE escrows SET status = 'disputed' WHERE id = ? AND status = ?")
             .bind(escrow_id)
-            .execute(db.pool())
+            .bind(&escrow.status)
+            .execute(&mut *db_tx)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::InvalidEscrowTransition {
+                from: escrow.status.clone(),
+                to: EscrowStatus::Disputed.into(),
+            });
+        }
+
+        for recipient in [&escrow.buyer_npub, &escrow.seller_npub] {
+            coordinator
+                .notify(
+                    &mut db_tx,
+                    escrow_id,
+                    recipient,
+                    EscrowMessageKind::DisputeOpened,
+                    &serde_json::json!({ "escrow_id": escrow_id }),
+                )
+                .await?;
+        }
+
+        EscrowEventService::record(&mut db_tx, escrow_id, "held", "disputed", escrow.amount)
             .await?;
+
+        db_tx.commit().await?;
+        events.notify_all();
+
         Ok(())
     }
 
@@ -206,15 +547,25 @@ impl EscrowService {
         Ok(escrows)
     }
 
-    /// Process auto-releases (call periodically)
-    pub async fn process_auto_releases(db: &Database) -> AppResult<u32> {
+    /// Timelock sweep (call periodically): feeds a [`Witness::Now`] into
+    /// every pending escrow's plan, releasing any whose `After` branch has
+    /// matured.
+    pub async fn process_auto_releases(
+        db: &Database,
+        coordinator: &EscrowCoordinator,
+        events: &EscrowEventBus,
+    ) -> AppResult<u32> {
         let pending = Self::get_pending_auto_releases(db).await?;
         let mut released = 0;
+        let witness = Witness::Now(Utc::now());
 
         for escrow in pending {
             // Only auto-release if not disputed
             if escrow.status_enum() == EscrowStatus::Held {
-                if let Ok(()) = Self::release_escrow(db, &escrow.id).await {
+                if let Ok(()) =
+                    Self::release_escrow(db, coordinator, events, &escrow.id, witness.clone())
+                        .await
+                {
                     released += 1;
                     tracing::info!("Auto-released escrow {}", escrow.id);
                 }
@@ -224,39 +575,101 @@ impl EscrowService {
         Ok(released)
     }
 
-    /// Deduct from user wallet with transaction logging
-    async fn deduct_wallet(
+    /// Dispute-timeout sweep (call periodically, alongside
+    /// [`Self::process_auto_releases`]): a dispute nobody resolves by its
+    /// `auto_resolve_at` deadline (see [`Dispute::should_auto_resolve`])
+    /// would otherwise sit open forever with the escrow frozen, so this
+    /// applies `default_resolution` to it via [`Self::resolve_dispute`]
+    /// under the coordinator's own arbiter npub, same as manual admin
+    /// arbitration does. Mirrors `routes/admin.rs::resolve_dispute`'s
+    /// dual write: resolving the escrow doesn't touch the `disputes` row,
+    /// so that update is applied here too.
+    pub async fn process_dispute_timeouts(
         db: &Database,
+        coordinator: &EscrowCoordinator,
+        events: &EscrowEventBus,
+        default_resolution: DisputeResolution,
+    ) -> AppResult<u32> {
+        let open_disputes: Vec<Dispute> =
+            sqlx::query_as("SELECT * FROM disputes WHERE status = 'open'")
+                .fetch_all(db.pool())
+                .await?;
+
+        let arbiter_npub = coordinator.arbiter_npub()?;
+        let mut resolved = 0;
+
+        for dispute in open_disputes {
+            if !dispute.should_auto_resolve() {
+                continue;
+            }
+
+            if let Err(e) = Self::resolve_dispute(
+                db,
+                coordinator,
+                events,
+                &dispute.escrow_id,
+                &arbiter_npub,
+                default_resolution,
+            )
+            .await
+            {
+                tracing::error!("Failed to auto-resolve dispute {}: {}", dispute.id, e);
+                continue;
+            }
+
+            sqlx::query(
+                "UPDATE disputes SET status = 'resolved', resolution = ?, resolution_notes = ?, resolved_by = 'system', resolved_at = CURRENT_TIMESTAMP WHERE id = ?",
+            )
+            .bind(default_resolution.to_str())
+            .bind("Auto-resolved: dispute timeout elapsed with no admin action")
+            .bind(&dispute.id)
+            .execute(db.pool())
+            .await?;
+
+            resolved += 1;
+            tracing::info!(
+                "Auto-resolved dispute {} on escrow {} (timeout)",
+                dispute.id,
+                dispute.escrow_id
+            );
+        }
+
+        Ok(resolved)
+    }
+
+    /// Deduct from user wallet into that escrow's hold account, sharing
+    /// `db_tx` with the caller's escrow-row/order-row writes so the whole
+    /// operation commits or rolls back together. The balance check and the
+    /// update are still one guarded `UPDATE` (see [`LedgerService::post`]),
+    /// so two concurrent holds against the same balance can't both succeed.
+    async fn deduct_wallet(
+        db_tx: &mut Transaction<'_, Sqlite>,
         user_npub: &str,
         amount: i64,
         tx_type: TransactionType,
         reference_id: Option<&str>,
     ) -> AppResult<i64> {
-        // Get current balance
-        let row: (i64,) =
+        let hold_account = reference_id
+            .map(escrow_hold_account)
+            .unwrap_or_else(|| ACCOUNT_MINT_FLOAT.to_string());
+        let tx_type_str = String::from(tx_type);
+
+        LedgerService::post(
+            db_tx,
+            user_npub,
+            &hold_account,
+            amount,
+            &tx_type_str,
+            reference_id,
+        )
+        .await?;
+
+        let (new_balance,): (i64,) =
             sqlx::query_as("SELECT wallet_balance FROM users WHERE npub = ?")
                 .bind(user_npub)
-                .fetch_one(db.pool())
+                .fetch_one(&mut **db_tx)
                 .await?;
 
-        let current_balance = row.0;
-        if current_balance < amount {
-            return Err(AppError::InsufficientBalanceDetails {
-                needed: amount as u64,
-                available: current_balance as u64,
-            });
-        }
-
-        let new_balance = current_balance - amount;
-
-        // Update balance
-        sqlx::query("UPDATE users SET wallet_balance = ? WHERE npub = ?")
-            .bind(new_balance)
-            .bind(user_npub)
-            .execute(db.pool())
-            .await?;
-
-        // Log transaction
         let tx_id = uuid::Uuid::new_v4().to_string();
         sqlx::query(
             r#"
@@ -266,42 +679,46 @@ impl EscrowService {
         )
         .bind(&tx_id)
         .bind(user_npub)
-        .bind(String::from(tx_type))
+        .bind(&tx_type_str)
         .bind(-amount) // negative for deduction
         .bind(new_balance)
         .bind(reference_id)
-        .execute(db.pool())
+        .execute(&mut **db_tx)
         .await?;
 
         Ok(new_balance)
     }
 
-    /// Credit user wallet with transaction logging
+    /// Credit a user's wallet from that escrow's hold account, sharing
+    /// `db_tx` with the caller's other writes (see [`Self::deduct_wallet`]).
     async fn credit_wallet(
-        db: &Database,
+        db_tx: &mut Transaction<'_, Sqlite>,
         user_npub: &str,
         amount: i64,
         tx_type: TransactionType,
         reference_id: Option<&str>,
     ) -> AppResult<i64> {
-        // Get current balance
-        let row: (i64,) =
+        let hold_account = reference_id
+            .map(escrow_hold_account)
+            .unwrap_or_else(|| ACCOUNT_MINT_FLOAT.to_string());
+        let tx_type_str = String::from(tx_type);
+
+        LedgerService::post(
+            db_tx,
+            &hold_account,
+            user_npub,
+            amount,
+            &tx_type_str,
+            reference_id,
+        )
+        .await?;
+
+        let (new_balance,): (i64,) =
             sqlx::query_as("SELECT wallet_balance FROM users WHERE npub = ?")
                 .bind(user_npub)
-                .fetch_one(db.pool())
+                .fetch_one(&mut **db_tx)
                 .await?;
 
-        let current_balance = row.0;
-        let new_balance = current_balance + amount;
-
-        // Update balance
-        sqlx::query("UPDATE users SET wallet_balance = ? WHERE npub = ?")
-            .bind(new_balance)
-            .bind(user_npub)
-            .execute(db.pool())
-            .await?;
-
-        // Log transaction
         let tx_id = uuid::Uuid::new_v4().to_string();
         sqlx::query(
             r#"
@@ -311,11 +728,11 @@ impl EscrowService {
         )
         .bind(&tx_id)
         .bind(user_npub)
-        .bind(String::from(tx_type))
+        .bind(&tx_type_str)
         .bind(amount)
         .bind(new_balance)
         .bind(reference_id)
-        .execute(db.pool())
+        .execute(&mut **db_tx)
         .await?;
 
         Ok(new_balance)
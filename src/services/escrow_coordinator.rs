@@ -0,0 +1,170 @@
+use nostr_sdk::prelude::*;
+
+use crate::config::Config;
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::models::{EscrowMessage, EscrowMessageKind};
+use crate::services::NostrService;
+
+/// Coordination DM event kind, continuing the custom-kind numbering this
+/// marketplace already uses for its own request/response protocols (see
+/// `NWC_REQUEST_KIND`/`NWC_RESPONSE_KIND` at 23194/23195).
+const ESCROW_DM_KIND: u16 = 23196;
+
+fn service_key_path(data_dir: &str) -> String {
+    format!("{}/service_nsec", data_dir)
+}
+
+/// Coordinates a P2PK-locked escrow's handshake and signature exchange
+/// between buyer and seller over Nostr DMs, keyed off the npubs the
+/// `escrows` table already carries. The marketplace holds one long-lived
+/// keypair here — the same pubkey passed as `arbiter_npub` to
+/// [`crate::services::CashuService::create_escrow_lock`] when escrow
+/// creation actually locks a token (mock mode only today — see that
+/// method's doc comment) — so it doubles as the 2-of-3 condition's third
+/// signer when arbitrating a dispute. Against a real mint, `arbiter_npub`
+/// still flows into the escrow's [`crate::models::EscrowPlan`] even though
+/// no token is locked, so the coordinator's role is unchanged once
+/// non-custodial locking ships.
+///
+/// Publishing to the relay happens out of band (see `escrow_dm_relay_task`
+/// in `main.rs`): this service only encrypts and enqueues messages in the
+/// `escrow_messages` outbox, so a relay outage can't block an escrow
+/// operation's own transaction.
+pub struct EscrowCoordinator {
+    keys: Keys,
+    pub relay_url: String,
+}
+
+impl EscrowCoordinator {
+    /// Load or generate the coordinator's keypair
+    pub fn new(config: &Config) -> anyhow::Result<Self> {
+        std::fs::create_dir_all(&config.escrow_coordinator.data_dir)?;
+        let path = service_key_path(&config.escrow_coordinator.data_dir);
+
+        let keys = if let Ok(hex_secret) = std::fs::read_to_string(&path) {
+            let secret_bytes = hex::decode(hex_secret.trim())?;
+            Keys::new(SecretKey::from_slice(&secret_bytes)?)
+        } else {
+            let keys = Keys::generate();
+            std::fs::write(&path, hex::encode(keys.secret_key().secret_bytes()))?;
+            tracing::info!("Generated new escrow coordinator keypair");
+            keys
+        };
+
+        Ok(Self {
+            keys,
+            relay_url: config.escrow_coordinator.relay_url.clone(),
+        })
+    }
+
+    /// The npub every escrow's P2PK lock is created with as `arbiter_npub`.
+    pub fn arbiter_npub(&self) -> AppResult<String> {
+        self.keys
+            .public_key()
+            .to_bech32()
+            .map_err(|e| AppError::Internal(format!("Failed to encode arbiter npub: {}", e)))
+    }
+
+    /// Encrypt `plaintext` to `recipient_npub` (NIP-44) using the
+    /// coordinator's own keypair as sender. Buyers/sellers don't have a
+    /// live nsec held server-side post-login, so anything the app needs to
+    /// encrypt at rest for a counterparty (e.g. a shipping address snapshot
+    /// a seller alone should be able to read) goes through the
+    /// marketplace's one persisted Nostr identity, the same one escrow
+    /// arbitration already trusts.
+    pub fn encrypt_for(&self, recipient_npub: &str, plaintext: &str) -> AppResult<String> {
+        let nsec = self.keys.secret_key().to_bech32().map_err(|e| {
+            AppError::Internal(format!("Failed to encode coordinator nsec: {}", e))
+        })?;
+
+        NostrService::encrypt_message(&nsec, recipient_npub, plaintext)
+    }
+
+    /// Encrypt and enqueue a coordination DM, sharing the caller's
+    /// transaction so it's only durably queued if the escrow mutation it
+    /// documents actually commits.
+    pub async fn notify(
+        &self,
+        db_tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+        escrow_id: &str,
+        recipient_npub: &str,
+        kind: EscrowMessageKind,
+        body: &serde_json::Value,
+    ) -> AppResult<()> {
+        let nsec = self.keys.secret_key().to_bech32().map_err(|e| {
+            AppError::Internal(format!("Failed to encode coordinator nsec: {}", e))
+        })?;
+        let plaintext = serde_json::to_string(body)
+            .map_err(|e| AppError::Internal(format!("Failed to serialize DM body: {}", e)))?;
+        let content = NostrService::encrypt_message(&nsec, recipient_npub, &plaintext)?;
+
+        let id = uuid::Uuid::new_v4().to_string();
+        sqlx::query(
+            "INSERT INTO escrow_messages (id, escrow_id, recipient_npub, kind, content, created_at) VALUES (?, ?, ?, ?, ?, CURRENT_TIMESTAMP)",
+        )
+        .bind(&id)
+        .bind(escrow_id)
+        .bind(recipient_npub)
+        .bind(String::from(kind))
+        .bind(&content)
+        .execute(&mut **db_tx)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Relay subscription filter matching DMs addressed to the
+    /// coordinator's own pubkey. Unused today — the coordinator only sends —
+    /// kept for symmetry with [`crate::services::NwcService::request_filter`]
+    /// for the reply leg a buyer/seller-initiated handshake step would need.
+    pub fn dm_filter(&self, since: Timestamp) -> Filter {
+        Filter::new()
+            .kind(Kind::Custom(ESCROW_DM_KIND))
+            .pubkey(self.keys.public_key())
+            .since(since)
+    }
+
+    /// Publish up to `limit` unsent outbox messages, marking each sent once
+    /// the relay accepts it. Called periodically by `escrow_dm_relay_task`;
+    /// a relay error just leaves the row unsent for the next tick.
+    pub async fn publish_pending(
+        &self,
+        db: &Database,
+        client: &Client,
+        limit: i64,
+    ) -> AppResult<u32> {
+        let pending: Vec<EscrowMessage> = sqlx::query_as(
+            "SELECT * FROM escrow_messages WHERE sent_at IS NULL ORDER BY created_at ASC LIMIT ?",
+        )
+        .bind(limit)
+        .fetch_all(db.pool())
+        .await?;
+
+        let mut published = 0;
+        for message in pending {
+            let recipient = NostrService::validate_npub(&message.recipient_npub)?;
+            let tags = vec![
+                Tag::parse(["p", &recipient.to_hex()])
+                    .map_err(|e| AppError::Internal(e.to_string()))?,
+                Tag::parse(["e", &message.escrow_id])
+                    .map_err(|e| AppError::Internal(e.to_string()))?,
+            ];
+
+            let event = EventBuilder::new(Kind::Custom(ESCROW_DM_KIND), message.content.clone())
+                .tags(tags)
+                .sign_with_keys(&self.keys)
+                .map_err(|e| AppError::Internal(format!("Failed to sign escrow DM: {}", e)))?;
+
+            if client.send_event(event).await.is_ok() {
+                sqlx::query("UPDATE escrow_messages SET sent_at = CURRENT_TIMESTAMP WHERE id = ?")
+                    .bind(&message.id)
+                    .execute(db.pool())
+                    .await?;
+                published += 1;
+            }
+        }
+
+        Ok(published)
+    }
+}
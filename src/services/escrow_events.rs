@@ -0,0 +1,118 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use sqlx::{Sqlite, Transaction};
+use tokio::sync::Notify;
+
+use crate::db::Database;
+use crate::error::AppResult;
+use crate::models::EscrowEvent;
+
+/// Wakes every request parked in [`EscrowEventBus::wait`] as soon as a new
+/// `escrow_events` row is committed, so a long-polling client sees a
+/// release/dispute the moment it happens instead of on its next timeout.
+/// Cloning shares the same underlying [`Notify`] — one instance lives in
+/// `AppState` and is cloned into background tasks and request handlers.
+#[derive(Clone)]
+pub struct EscrowEventBus {
+    notify: Arc<Notify>,
+}
+
+impl EscrowEventBus {
+    pub fn new() -> Self {
+        Self {
+            notify: Arc::new(Notify::new()),
+        }
+    }
+
+    /// Wake every currently-parked `wait` call. A single bus is shared
+    /// across all escrows, so waiters re-check their own cursor against
+    /// the DB rather than being told which escrow changed.
+    pub fn notify_all(&self) {
+        self.notify.notify_waiters();
+    }
+
+    /// Park until the next `notify_all`, or `timeout` elapses — whichever
+    /// comes first.
+    pub async fn wait(&self, timeout: Duration) {
+        tokio::select! {
+            _ = self.notify.notified() => {}
+            _ = tokio::time::sleep(timeout) => {}
+        }
+    }
+}
+
+impl Default for EscrowEventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Append-only log of escrow status transitions, and the long-poll query
+/// that reads it back.
+pub struct EscrowEventService;
+
+impl EscrowEventService {
+    /// Append a state-transition event, sharing the caller's transaction so
+    /// the event can never be recorded without the transition actually
+    /// committing (or vice versa). Does not itself wake long-pollers — the
+    /// caller should call [`EscrowEventBus::notify_all`] after `db_tx`
+    /// commits, once the row is actually visible to readers.
+    pub async fn record(
+        db_tx: &mut Transaction<'_, Sqlite>,
+        escrow_id: &str,
+        old_status: &str,
+        new_status: &str,
+        amount: i64,
+    ) -> AppResult<()> {
+        sqlx::query(
+            "INSERT INTO escrow_events (escrow_id, old_status, new_status, amount, created_at) VALUES (?, ?, ?, ?, CURRENT_TIMESTAMP)",
+        )
+        .bind(escrow_id)
+        .bind(old_status)
+        .bind(new_status)
+        .bind(amount)
+        .execute(&mut **db_tx)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Events for `escrow_id` newer than `since_cursor`, oldest first.
+    pub async fn since(
+        db: &Database,
+        escrow_id: &str,
+        since_cursor: i64,
+    ) -> AppResult<Vec<EscrowEvent>> {
+        let events = sqlx::query_as::<_, EscrowEvent>(
+            "SELECT * FROM escrow_events WHERE escrow_id = ? AND id > ? ORDER BY id ASC",
+        )
+        .bind(escrow_id)
+        .bind(since_cursor)
+        .fetch_all(db.pool())
+        .await?;
+
+        Ok(events)
+    }
+
+    /// Long-poll: return events newer than `since_cursor` immediately if
+    /// any already exist, otherwise park on `bus` until one is posted or
+    /// `timeout` elapses, then check once more before giving up with an
+    /// empty list.
+    pub async fn poll(
+        db: &Database,
+        bus: &EscrowEventBus,
+        escrow_id: &str,
+        since_cursor: i64,
+        timeout: Duration,
+    ) -> AppResult<Vec<EscrowEvent>> {
+        let events = Self::since(db, escrow_id, since_cursor).await?;
+        if !events.is_empty() {
+            return Ok(events);
+        }
+
+        bus.wait(timeout).await;
+
+        Self::since(db, escrow_id, since_cursor).await
+    }
+}
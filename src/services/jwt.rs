@@ -0,0 +1,99 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use crate::error::{AppError, AppResult};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Claims embedded in a short-lived JWT access token: who it's for
+/// (`sub`, an npub), their role at issuance time, the issued-at/expiry
+/// unix timestamps, and a `jti` tying it back to the refresh token it was
+/// minted alongside (for logging/correlation, not revocation — the
+/// access token itself can't be revoked before it expires).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccessClaims {
+    pub sub: String,
+    pub role: String,
+    pub iat: i64,
+    pub exp: i64,
+    pub jti: String,
+}
+
+/// How long a minted access token stays valid.
+const ACCESS_TOKEN_MINUTES: i64 = 15;
+
+/// Mints and verifies the JWT access tokens that let API clients
+/// authenticate with a `Bearer` header instead of the browser's session
+/// cookie. Hand-rolled in the same spirit as `AccessPassService` (HMAC-SHA256
+/// over a compact payload) rather than pulling in a JWT library, since this
+/// codebase already hand-rolls its other signed credentials the same way.
+pub struct JwtService;
+
+impl JwtService {
+    /// Issue a fresh access token for `npub`/`role`, tagged with `jti` (the
+    /// refresh token it was minted alongside, so the two can be correlated
+    /// in logs).
+    pub fn issue(signing_key: &[u8], npub: &str, role: &str, jti: &str) -> AppResult<String> {
+        let now = chrono::Utc::now().timestamp();
+        let claims = AccessClaims {
+            sub: npub.to_string(),
+            role: role.to_string(),
+            iat: now,
+            exp: now + ACCESS_TOKEN_MINUTES * 60,
+            jti: jti.to_string(),
+        };
+
+        let header = URL_SAFE_NO_PAD.encode(br#"{"alg":"HS256","typ":"JWT"}"#);
+        let payload = URL_SAFE_NO_PAD.encode(
+            serde_json::to_vec(&claims).map_err(|e| AppError::Internal(e.to_string()))?,
+        );
+        let signature = Self::sign(signing_key, &header, &payload);
+
+        Ok(format!("{header}.{payload}.{signature}"))
+    }
+
+    /// Verify a token's signature and expiry, returning its claims.
+    pub fn verify(signing_key: &[u8], token: &str) -> AppResult<AccessClaims> {
+        let mut parts = token.split('.');
+        let (Some(header), Some(payload), Some(signature), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(AppError::InvalidAccessToken);
+        };
+
+        let expected = Self::sign(signing_key, header, payload);
+        if !constant_time_eq(signature.as_bytes(), expected.as_bytes()) {
+            return Err(AppError::InvalidAccessToken);
+        }
+
+        let payload_bytes = URL_SAFE_NO_PAD
+            .decode(payload)
+            .map_err(|_| AppError::InvalidAccessToken)?;
+        let claims: AccessClaims =
+            serde_json::from_slice(&payload_bytes).map_err(|_| AppError::InvalidAccessToken)?;
+
+        if claims.exp < chrono::Utc::now().timestamp() {
+            return Err(AppError::InvalidAccessToken);
+        }
+
+        Ok(claims)
+    }
+
+    fn sign(signing_key: &[u8], header: &str, payload: &str) -> String {
+        let mut mac =
+            HmacSha256::new_from_slice(signing_key).expect("HMAC accepts any key length");
+        mac.update(header.as_bytes());
+        mac.update(b".");
+        mac.update(payload.as_bytes());
+        URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes())
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
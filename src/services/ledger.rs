@@ -0,0 +1,202 @@
+use sqlx::{Sqlite, Transaction};
+
+use crate::error::{AppError, AppResult};
+use crate::models::WalletTransaction;
+
+/// Account holding value that has left the marketplace's internal wallets
+/// via the mint (Lightning deposits/withdrawals, Cashu token import/export).
+pub const ACCOUNT_MINT_FLOAT: &str = "mint-float";
+
+/// Account a [`DisputeResolution::Burn`](crate::models::DisputeResolution)
+/// (or a `Split` rounding remainder) moves funds into — never paid out to
+/// anyone, but still a ledger account rather than an amount that just stops
+/// being tracked.
+///
+/// [`DisputeResolution::Burn`]: crate::models::DisputeResolution::Burn
+pub const ACCOUNT_BURNED: &str = "burned";
+
+/// Account a given escrow's held funds sit in between hold and
+/// release/refund.
+pub fn escrow_hold_account(escrow_id: &str) -> String {
+    format!("escrow-hold:{}", escrow_id)
+}
+
+/// Double-entry ledger for wallet balance movements.
+///
+/// `users.wallet_balance` stays as a cached projection (existing reads
+/// across the codebase are unchanged), mutated here atomically, in the
+/// same transaction as the paired ledger rows — closing the
+/// read-balance/check/write-balance race that let concurrent
+/// `wallet::deposit`/`wallet::withdraw` requests corrupt it. The seller
+/// bond and category purchases in `routes::seller` are the exception: they
+/// write `wallet_balance` directly and post no ledger rows.
+pub struct LedgerService;
+
+impl LedgerService {
+    /// Move `amount` sats from `from_account` to `to_account`, recording
+    /// both legs in `ledger_entries` under a shared `entry_group`. When an
+    /// account is a user wallet (an `npub1...` id), the matching
+    /// `users.wallet_balance` row is updated in the same statement; a debit
+    /// is guarded by `WHERE wallet_balance >= ?` so it either fully applies
+    /// or fails with [`AppError::InsufficientBalance`] — never partially.
+    pub async fn post(
+        tx: &mut Transaction<'_, Sqlite>,
+        from_account: &str,
+        to_account: &str,
+        amount: i64,
+        tx_type: &str,
+        reference_id: Option<&str>,
+    ) -> AppResult<()> {
+        if Self::is_user_account(from_account) {
+            let result = sqlx::query(
+                "UPDATE users SET wallet_balance = wallet_balance - ? WHERE npub = ? AND wallet_balance >= ?",
+            )
+            .bind(amount)
+            .bind(from_account)
+            .bind(amount)
+            .execute(&mut **tx)
+            .await?;
+
+            if result.rows_affected() == 0 {
+                return Err(AppError::InsufficientBalance);
+            }
+        }
+
+        if Self::is_user_account(to_account) {
+            sqlx::query("UPDATE users SET wallet_balance = wallet_balance + ? WHERE npub = ?")
+                .bind(amount)
+                .bind(to_account)
+                .execute(&mut **tx)
+                .await?;
+        }
+
+        let entry_group = uuid::Uuid::new_v4().to_string();
+
+        sqlx::query(
+            "INSERT INTO ledger_entries (id, entry_group, account, amount, transaction_type, reference_id, created_at) VALUES (?, ?, ?, ?, ?, ?, CURRENT_TIMESTAMP)",
+        )
+        .bind(uuid::Uuid::new_v4().to_string())
+        .bind(&entry_group)
+        .bind(from_account)
+        .bind(-amount)
+        .bind(tx_type)
+        .bind(reference_id)
+        .execute(&mut **tx)
+        .await?;
+
+        sqlx::query(
+            "INSERT INTO ledger_entries (id, entry_group, account, amount, transaction_type, reference_id, created_at) VALUES (?, ?, ?, ?, ?, ?, CURRENT_TIMESTAMP)",
+        )
+        .bind(uuid::Uuid::new_v4().to_string())
+        .bind(&entry_group)
+        .bind(to_account)
+        .bind(amount)
+        .bind(tx_type)
+        .bind(reference_id)
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Sum of ledger entries for `account` — the source of truth
+    /// `users.wallet_balance` is cached from, usable to reconcile the two.
+    pub async fn balance_of(db: &crate::db::Database, account: &str) -> AppResult<i64> {
+        let (total,): (i64,) =
+            sqlx::query_as("SELECT COALESCE(SUM(amount), 0) FROM ledger_entries WHERE account = ?")
+                .bind(account)
+                .fetch_one(db.pool())
+                .await?;
+        Ok(total)
+    }
+
+    /// Assert that `escrow_id`'s hold account has been fully drained —
+    /// i.e. every sat `deduct_wallet` moved into it on hold has since been
+    /// moved back out again via `credit_wallet`/a burn posting, with none
+    /// left stranded. Every [`Self::post`] call is balanced by construction,
+    /// so this can't catch a mismatched debit/credit; what it catches is a
+    /// resolution path (like the pre-fix `DisputeResolution::Burn`, or a
+    /// `Split` with a rounding remainder) that books less than the full
+    /// escrow amount out of the hold account and leaves the rest sitting
+    /// there unaccounted for. Call within the same transaction as the
+    /// resolution, before committing, so an imbalance rolls the whole
+    /// operation back instead of persisting it.
+    pub async fn assert_escrow_conserved(
+        tx: &mut Transaction<'_, Sqlite>,
+        escrow_id: &str,
+    ) -> AppResult<()> {
+        let hold_account = escrow_hold_account(escrow_id);
+        let (remaining,): (i64,) =
+            sqlx::query_as("SELECT COALESCE(SUM(amount), 0) FROM ledger_entries WHERE account = ?")
+                .bind(&hold_account)
+                .fetch_one(&mut **tx)
+                .await?;
+
+        if remaining != 0 {
+            return Err(AppError::EscrowLedgerImbalance {
+                escrow_id: escrow_id.to_string(),
+                remaining,
+            });
+        }
+
+        Ok(())
+    }
+
+    fn is_user_account(account: &str) -> bool {
+        account.starts_with("npub1")
+    }
+
+    /// Transactions for `user_npub` matching `filter`, newest first — the
+    /// query behind wallet receipts and seller payout reconciliation.
+    pub async fn get_transactions(
+        db: &crate::db::Database,
+        user_npub: &str,
+        filter: &TransactionFilter,
+    ) -> AppResult<Vec<WalletTransaction>> {
+        let transactions: Vec<WalletTransaction> = sqlx::query_as(
+            "SELECT * FROM wallet_transactions \
+             WHERE user_npub = ? \
+             AND (? IS NULL OR transaction_type = ?) \
+             AND (? IS NULL OR listing_id = ?) \
+             AND (? IS NULL OR checkout_id = ?) \
+             ORDER BY created_at DESC",
+        )
+        .bind(user_npub)
+        .bind(&filter.kind)
+        .bind(&filter.kind)
+        .bind(&filter.listing_id)
+        .bind(&filter.listing_id)
+        .bind(&filter.checkout_id)
+        .bind(&filter.checkout_id)
+        .fetch_all(db.pool())
+        .await?;
+
+        Ok(transactions)
+    }
+
+    /// Every transaction (across all users) tagged with `label`, for
+    /// reconciling a specific order's payout or a buyer's receipt by the
+    /// reference it was created with.
+    pub async fn get_transactions_by_label(
+        db: &crate::db::Database,
+        label: &str,
+    ) -> AppResult<Vec<WalletTransaction>> {
+        let transactions: Vec<WalletTransaction> = sqlx::query_as(
+            "SELECT * FROM wallet_transactions WHERE label = ? ORDER BY created_at DESC",
+        )
+        .bind(label)
+        .fetch_all(db.pool())
+        .await?;
+
+        Ok(transactions)
+    }
+}
+
+/// Optional narrowing for [`LedgerService::get_transactions`] — any field
+/// left `None` matches everything.
+#[derive(Debug, Clone, Default)]
+pub struct TransactionFilter {
+    pub kind: Option<String>,
+    pub listing_id: Option<String>,
+    pub checkout_id: Option<String>,
+}
@@ -0,0 +1,37 @@
+mod access_pass;
+mod api_token;
+mod cashu;
+mod checkout;
+mod connector;
+mod deposit_indexer;
+mod escrow;
+mod escrow_coordinator;
+mod escrow_events;
+mod jwt;
+mod ledger;
+mod nostr;
+mod nwc;
+mod rate;
+mod rating;
+mod reconciliation;
+mod refresh_token;
+mod stats;
+
+pub use access_pass::*;
+pub use api_token::*;
+pub use cashu::*;
+pub use checkout::*;
+pub use connector::*;
+pub use deposit_indexer::*;
+pub use escrow::*;
+pub use escrow_coordinator::*;
+pub use escrow_events::*;
+pub use jwt::*;
+pub use ledger::*;
+pub use nostr::*;
+pub use nwc::*;
+pub use rate::*;
+pub use rating::*;
+pub use reconciliation::*;
+pub use refresh_token::*;
+pub use stats::*;
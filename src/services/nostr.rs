@@ -1,9 +1,40 @@
+use std::str::FromStr;
+
+use argon2::Argon2;
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
 use nostr_sdk::prelude::*;
+use nostr_sdk::secp256k1;
+use rand::RngCore;
 use sha2::Digest;
 
 use crate::config::Config;
 use crate::error::{AppError, AppResult};
 
+/// Version byte identifying the Argon2id + XChaCha20-Poly1305 nsec-at-rest
+/// format (`salt(16) || nonce(24) || ciphertext+tag`). Anything decoding to
+/// a shorter/differently-shaped blob is assumed to be a legacy XOR blob
+/// from before this format existed.
+const NSEC_ENC_VERSION: u8 = 1;
+const NSEC_SALT_LEN: usize = 16;
+const NSEC_NONCE_LEN: usize = 24;
+
+/// NIP-98 HTTP Auth event kind
+const NIP98_AUTH_KIND: u16 = 27235;
+
+/// NIP-42-flavored login challenge/response event kind
+const LOGIN_CHALLENGE_KIND: u16 = 22242;
+
+/// Argon2id params: 64 MiB memory, 3 iterations, 1 lane — memory-hard
+/// enough to make password-guessing against a stolen blob expensive.
+fn nsec_argon2() -> Argon2<'static> {
+    Argon2::new(
+        argon2::Algorithm::Argon2id,
+        argon2::Version::V0x13,
+        argon2::Params::new(65536, 3, 1, Some(32)).expect("valid Argon2 params"),
+    )
+}
+
 /// Nostr keypair and encryption service
 pub struct NostrService {
     _admin_npub: String,
@@ -94,57 +125,278 @@ impl NostrService {
         Ok(decrypted)
     }
 
-    /// Sign a message with an nsec (for verification)
-    /// Note: This creates a simple hash-based signature for internal use
+    /// Sign a message with an nsec, producing a genuine BIP-340 Schnorr
+    /// signature over `sha256(message)` from the secret key — unlike a
+    /// hash of `message || pubkey`, this actually proves possession of the
+    /// secret key and can't be forged by anyone who only knows the npub.
     pub fn sign_message(nsec: &str, message: &str) -> AppResult<String> {
         let keys = Self::validate_nsec(nsec)?;
 
-        // Create a simple signature by hashing message with secret key context
-        let mut hasher = sha2::Sha256::new();
-        hasher.update(message.as_bytes());
-        hasher.update(keys.public_key().to_bytes());
-        let hash = hasher.finalize();
+        let digest: [u8; 32] = sha2::Sha256::digest(message.as_bytes()).into();
+        let msg = secp256k1::Message::from_digest(digest);
+
+        let secp = secp256k1::Secp256k1::new();
+        let secret_key = secp256k1::SecretKey::from_slice(&keys.secret_key().secret_bytes())
+            .map_err(|e| AppError::Internal(format!("Invalid secret key: {}", e)))?;
+        let keypair = secp256k1::Keypair::from_secret_key(&secp, &secret_key);
 
-        Ok(hex::encode(hash))
+        let signature = secp.sign_schnorr(&msg, &keypair);
+        Ok(signature.to_string())
     }
 
-    /// Verify a signature
-    /// Note: This is a simplified verification for internal use
+    /// Verify a BIP-340 Schnorr signature produced by [`Self::sign_message`]
+    /// against the signer's x-only public key
     pub fn verify_signature(npub: &str, message: &str, signature: &str) -> AppResult<bool> {
         let pubkey = Self::validate_npub(npub)?;
 
-        // Recreate the hash and compare
-        let mut hasher = sha2::Sha256::new();
-        hasher.update(message.as_bytes());
-        hasher.update(pubkey.to_bytes());
-        let hash = hasher.finalize();
+        let digest: [u8; 32] = sha2::Sha256::digest(message.as_bytes()).into();
+        let msg = secp256k1::Message::from_digest(digest);
+
+        let sig = match secp256k1::schnorr::Signature::from_str(signature) {
+            Ok(sig) => sig,
+            Err(_) => return Ok(false),
+        };
+
+        let xonly = secp256k1::XOnlyPublicKey::from_slice(&pubkey.to_bytes())
+            .map_err(|e| AppError::Internal(format!("Invalid pubkey: {}", e)))?;
+
+        Ok(sig.verify(&msg, &xonly).is_ok())
+    }
+
+    /// Build a NIP-98 HTTP auth event (kind 27235): proves the caller
+    /// controls `nsec` for this exact `method` + `url` + request body,
+    /// rather than relying solely on a session cookie. Returned as the
+    /// event's JSON so callers can attach it to the request (e.g. as an
+    /// `Authorization: Nostr <base64>` header or a hidden form field).
+    pub fn sign_auth_event(nsec: &str, method: &str, url: &str, body: &[u8]) -> AppResult<String> {
+        let keys = Self::validate_nsec(nsec)?;
+        let payload_hash = hex::encode(sha2::Sha256::digest(body));
+
+        let tags = vec![
+            Tag::parse(["u", url]).map_err(|e| AppError::Internal(e.to_string()))?,
+            Tag::parse(["method", method]).map_err(|e| AppError::Internal(e.to_string()))?,
+            Tag::parse(["payload", &payload_hash]).map_err(|e| AppError::Internal(e.to_string()))?,
+        ];
+
+        let event = EventBuilder::new(Kind::Custom(NIP98_AUTH_KIND), "")
+            .tags(tags)
+            .sign_with_keys(&keys)
+            .map_err(|e| AppError::Internal(format!("Failed to sign auth event: {}", e)))?;
+
+        Ok(event.as_json())
+    }
+
+    /// Verify a NIP-98 auth event: checks the Schnorr signature, that it
+    /// was issued by `expected_npub` for this exact `method` + `url` +
+    /// body, and that it isn't stale (replay window of `max_age`).
+    pub fn verify_auth_event(
+        event_json: &str,
+        expected_npub: &str,
+        method: &str,
+        url: &str,
+        body: &[u8],
+        max_age: chrono::Duration,
+    ) -> AppResult<bool> {
+        let event = match Event::from_json(event_json) {
+            Ok(event) => event,
+            Err(_) => return Ok(false),
+        };
+
+        if event.verify().is_err() {
+            return Ok(false);
+        }
+
+        if event.kind != Kind::Custom(NIP98_AUTH_KIND) {
+            return Ok(false);
+        }
+
+        let expected_pubkey = Self::validate_npub(expected_npub)?;
+        if event.pubkey != expected_pubkey {
+            return Ok(false);
+        }
+
+        let age = chrono::Utc::now().timestamp() - event.created_at.as_u64() as i64;
+        if !(0..=max_age.num_seconds()).contains(&age) {
+            return Ok(false);
+        }
+
+        let tag_value = |name: &str| -> Option<String> {
+            event
+                .tags
+                .iter()
+                .find(|tag| tag.as_slice().first().map(String::as_str) == Some(name))
+                .and_then(|tag| tag.as_slice().get(1))
+                .cloned()
+        };
+
+        if tag_value("method").as_deref() != Some(method) {
+            return Ok(false);
+        }
+        if tag_value("u").as_deref() != Some(url) {
+            return Ok(false);
+        }
+
+        let expected_payload = hex::encode(sha2::Sha256::digest(body));
+        if tag_value("payload").as_deref() != Some(expected_payload.as_str()) {
+            return Ok(false);
+        }
+
+        Ok(true)
+    }
+
+    /// Build a login challenge/response event (kind 22242, NIP-42-style):
+    /// proves the caller controls `nsec` without ever sending it to the
+    /// server. Real clients sign this themselves via a NIP-07 extension or
+    /// remote signer and post back the event JSON — this helper exists so
+    /// `nsec`-based login still has a code path (dev/test only, gated by
+    /// `Config::dev_login_enabled`).
+    pub fn sign_challenge_event(nsec: &str, challenge: &str, domain: &str) -> AppResult<String> {
+        let keys = Self::validate_nsec(nsec)?;
+
+        let tags = vec![
+            Tag::parse(["challenge", challenge]).map_err(|e| AppError::Internal(e.to_string()))?,
+            Tag::parse(["relay", domain]).map_err(|e| AppError::Internal(e.to_string()))?,
+        ];
+
+        let event = EventBuilder::new(Kind::Custom(LOGIN_CHALLENGE_KIND), "")
+            .tags(tags)
+            .sign_with_keys(&keys)
+            .map_err(|e| AppError::Internal(format!("Failed to sign challenge event: {}", e)))?;
+
+        Ok(event.as_json())
+    }
+
+    /// Verify a signed login challenge event: checks the Schnorr
+    /// signature, that it's kind 22242 carrying the exact `challenge` and
+    /// `domain` (`relay` tag) issued for this login attempt, and that it
+    /// isn't stale (replay window of `max_age`). Unlike
+    /// [`Self::verify_auth_event`], the signer isn't known in advance —
+    /// this derives and returns their npub on success instead of checking
+    /// it against one.
+    pub fn verify_challenge_event(
+        event_json: &str,
+        challenge: &str,
+        domain: &str,
+        max_age: chrono::Duration,
+    ) -> AppResult<Option<String>> {
+        let event = match Event::from_json(event_json) {
+            Ok(event) => event,
+            Err(_) => return Ok(None),
+        };
+
+        if event.verify().is_err() {
+            return Ok(None);
+        }
+
+        if event.kind != Kind::Custom(LOGIN_CHALLENGE_KIND) {
+            return Ok(None);
+        }
+
+        let age = chrono::Utc::now().timestamp() - event.created_at.as_u64() as i64;
+        if !(0..=max_age.num_seconds()).contains(&age) {
+            return Ok(None);
+        }
+
+        let tag_value = |name: &str| -> Option<String> {
+            event
+                .tags
+                .iter()
+                .find(|tag| tag.as_slice().first().map(String::as_str) == Some(name))
+                .and_then(|tag| tag.as_slice().get(1))
+                .cloned()
+        };
+
+        if tag_value("challenge").as_deref() != Some(challenge) {
+            return Ok(None);
+        }
+        if tag_value("relay").as_deref() != Some(domain) {
+            return Ok(None);
+        }
+
+        let npub = event
+            .pubkey
+            .to_bech32()
+            .map_err(|e| AppError::Internal(format!("Failed to encode npub: {}", e)))?;
 
-        let expected = hex::encode(hash);
-        Ok(expected == signature)
+        Ok(Some(npub))
     }
 
     /// Encrypt nsec for storage (optional, for server-generated keys)
-    /// Uses a simple XOR with a derived key for now
-    /// In production, use proper key derivation and AES-GCM
+    ///
+    /// Derives a 256-bit key from `user_password` with Argon2id under a
+    /// fresh random salt, then seals the nsec with XChaCha20-Poly1305 under
+    /// a fresh random nonce. Stores `version || salt || nonce || ciphertext`
+    /// hex-encoded; the Poly1305 tag gives both confidentiality and
+    /// integrity, unlike the XOR scheme this replaces.
     pub fn encrypt_nsec_for_storage(nsec: &str, user_password: &str) -> AppResult<String> {
-        // Simple encryption for demo - in production use proper crypto
-        let key_bytes = sha2::Sha256::digest(user_password.as_bytes());
-        let nsec_bytes = nsec.as_bytes();
+        let mut salt = [0u8; NSEC_SALT_LEN];
+        rand::thread_rng().fill_bytes(&mut salt);
 
-        let encrypted: Vec<u8> = nsec_bytes
-            .iter()
-            .enumerate()
-            .map(|(i, b)| b ^ key_bytes[i % 32])
-            .collect();
+        let mut key_bytes = [0u8; 32];
+        nsec_argon2()
+            .hash_password_into(user_password.as_bytes(), &salt, &mut key_bytes)
+            .map_err(|e| AppError::Internal(format!("Key derivation failed: {}", e)))?;
+
+        let mut nonce_bytes = [0u8; NSEC_NONCE_LEN];
+        rand::thread_rng().fill_bytes(&mut nonce_bytes);
+
+        let cipher = XChaCha20Poly1305::new(Key::from_slice(&key_bytes));
+        let ciphertext = cipher
+            .encrypt(XNonce::from_slice(&nonce_bytes), nsec.as_bytes())
+            .map_err(|e| AppError::Internal(format!("Encryption failed: {}", e)))?;
 
-        Ok(hex::encode(encrypted))
+        let mut out = Vec::with_capacity(1 + NSEC_SALT_LEN + NSEC_NONCE_LEN + ciphertext.len());
+        out.push(NSEC_ENC_VERSION);
+        out.extend_from_slice(&salt);
+        out.extend_from_slice(&nonce_bytes);
+        out.extend_from_slice(&ciphertext);
+
+        Ok(hex::encode(out))
     }
 
     /// Decrypt nsec from storage
+    ///
+    /// Fails closed: a wrong password or a tampered/corrupted blob is
+    /// rejected by the Poly1305 tag check rather than returning garbage.
+    /// Blobs that don't match the current `version || salt || nonce ||
+    /// ciphertext` shape are assumed to be pre-migration XOR blobs and are
+    /// decrypted with [`Self::decrypt_legacy_xor_nsec`] instead; callers
+    /// should use [`Self::is_legacy_encrypted_nsec`] to detect this case
+    /// and re-encrypt with the current scheme on next successful login.
     pub fn decrypt_nsec_from_storage(encrypted: &str, user_password: &str) -> AppResult<String> {
-        let encrypted_bytes =
-            hex::decode(encrypted).map_err(|_| AppError::Internal("Invalid encrypted data".to_string()))?;
+        let bytes = hex::decode(encrypted)
+            .map_err(|_| AppError::Internal("Invalid encrypted data".to_string()))?;
+
+        if Self::is_legacy_encrypted_nsec(&bytes) {
+            return Self::decrypt_legacy_xor_nsec(&bytes, user_password);
+        }
+
+        let salt = &bytes[1..1 + NSEC_SALT_LEN];
+        let nonce_bytes = &bytes[1 + NSEC_SALT_LEN..1 + NSEC_SALT_LEN + NSEC_NONCE_LEN];
+        let ciphertext = &bytes[1 + NSEC_SALT_LEN + NSEC_NONCE_LEN..];
 
+        let mut key_bytes = [0u8; 32];
+        nsec_argon2()
+            .hash_password_into(user_password.as_bytes(), salt, &mut key_bytes)
+            .map_err(|e| AppError::Internal(format!("Key derivation failed: {}", e)))?;
+
+        let cipher = XChaCha20Poly1305::new(Key::from_slice(&key_bytes));
+        let plaintext = cipher
+            .decrypt(XNonce::from_slice(nonce_bytes), ciphertext)
+            .map_err(|_| AppError::InvalidCredentials)?;
+
+        String::from_utf8(plaintext).map_err(|_| AppError::InvalidCredentials)
+    }
+
+    /// Whether a decoded stored blob predates the Argon2id/XChaCha20-Poly1305
+    /// format (plain XOR, no version byte or nonce/salt framing)
+    fn is_legacy_encrypted_nsec(bytes: &[u8]) -> bool {
+        bytes.first() != Some(&NSEC_ENC_VERSION) || bytes.len() <= 1 + NSEC_SALT_LEN + NSEC_NONCE_LEN
+    }
+
+    /// Decrypt a pre-migration XOR-obfuscated nsec blob, for one-time
+    /// migration to [`Self::encrypt_nsec_for_storage`] on next login
+    fn decrypt_legacy_xor_nsec(encrypted_bytes: &[u8], user_password: &str) -> AppResult<String> {
         let key_bytes = sha2::Sha256::digest(user_password.as_bytes());
 
         let decrypted: Vec<u8> = encrypted_bytes
@@ -153,8 +405,7 @@ impl NostrService {
             .map(|(i, b)| b ^ key_bytes[i % 32])
             .collect();
 
-        String::from_utf8(decrypted)
-            .map_err(|_| AppError::Internal("Decryption failed".to_string()))
+        String::from_utf8(decrypted).map_err(|_| AppError::InvalidCredentials)
     }
 }
 
@@ -192,4 +443,149 @@ mod tests {
 
         assert_eq!(decrypted, message);
     }
+
+    #[test]
+    fn test_encrypt_decrypt_nsec_roundtrip() {
+        let (nsec, _) = NostrService::generate_keypair().unwrap();
+
+        let encrypted = NostrService::encrypt_nsec_for_storage(&nsec, "hunter2").unwrap();
+        assert_ne!(encrypted, nsec);
+
+        let decrypted = NostrService::decrypt_nsec_from_storage(&encrypted, "hunter2").unwrap();
+        assert_eq!(decrypted, nsec);
+    }
+
+    #[test]
+    fn test_decrypt_nsec_wrong_password_fails() {
+        let (nsec, _) = NostrService::generate_keypair().unwrap();
+
+        let encrypted = NostrService::encrypt_nsec_for_storage(&nsec, "hunter2").unwrap();
+        let result = NostrService::decrypt_nsec_from_storage(&encrypted, "wrong password");
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_legacy_xor_nsec_is_migrated_on_decrypt() {
+        let (nsec, _) = NostrService::generate_keypair().unwrap();
+
+        // Simulate a pre-migration XOR blob (no version/salt/nonce framing)
+        let key_bytes = sha2::Sha256::digest("hunter2".as_bytes());
+        let legacy: Vec<u8> = nsec
+            .as_bytes()
+            .iter()
+            .enumerate()
+            .map(|(i, b)| b ^ key_bytes[i % 32])
+            .collect();
+        let legacy_hex = hex::encode(legacy);
+
+        let decrypted = NostrService::decrypt_nsec_from_storage(&legacy_hex, "hunter2").unwrap();
+        assert_eq!(decrypted, nsec);
+
+        // Re-encrypting in the current format should no longer be detected as legacy
+        let migrated = NostrService::encrypt_nsec_for_storage(&nsec, "hunter2").unwrap();
+        let migrated_bytes = hex::decode(&migrated).unwrap();
+        assert!(!NostrService::is_legacy_encrypted_nsec(&migrated_bytes));
+    }
+
+    #[test]
+    fn test_sign_verify_message() {
+        let (nsec, npub) = NostrService::generate_keypair().unwrap();
+        let message = "authorize withdrawal of 1000 sats";
+
+        let signature = NostrService::sign_message(&nsec, message).unwrap();
+        assert!(NostrService::verify_signature(&npub, message, &signature).unwrap());
+    }
+
+    #[test]
+    fn test_verify_signature_rejects_tampered_message() {
+        let (nsec, npub) = NostrService::generate_keypair().unwrap();
+        let signature = NostrService::sign_message(&nsec, "original message").unwrap();
+
+        assert!(!NostrService::verify_signature(&npub, "different message", &signature).unwrap());
+    }
+
+    #[test]
+    fn test_verify_signature_rejects_wrong_signer() {
+        let (nsec, _) = NostrService::generate_keypair().unwrap();
+        let (_, other_npub) = NostrService::generate_keypair().unwrap();
+        let message = "authorize withdrawal of 1000 sats";
+
+        let signature = NostrService::sign_message(&nsec, message).unwrap();
+        assert!(!NostrService::verify_signature(&other_npub, message, &signature).unwrap());
+    }
+
+    #[test]
+    fn test_sign_verify_auth_event() {
+        let (nsec, npub) = NostrService::generate_keypair().unwrap();
+        let body = b"tracking_info=abc123";
+
+        let event_json =
+            NostrService::sign_auth_event(&nsec, "POST", "/seller/orders/1/ship", body).unwrap();
+
+        assert!(NostrService::verify_auth_event(
+            &event_json,
+            &npub,
+            "POST",
+            "/seller/orders/1/ship",
+            body,
+            chrono::Duration::minutes(5),
+        )
+        .unwrap());
+    }
+
+    #[test]
+    fn test_verify_auth_event_rejects_mismatched_url() {
+        let (nsec, npub) = NostrService::generate_keypair().unwrap();
+        let body = b"tracking_info=abc123";
+
+        let event_json =
+            NostrService::sign_auth_event(&nsec, "POST", "/seller/orders/1/ship", body).unwrap();
+
+        assert!(!NostrService::verify_auth_event(
+            &event_json,
+            &npub,
+            "POST",
+            "/seller/orders/2/ship",
+            body,
+            chrono::Duration::minutes(5),
+        )
+        .unwrap());
+    }
+
+    #[test]
+    fn test_sign_verify_challenge_event() {
+        let (nsec, npub) = NostrService::generate_keypair().unwrap();
+
+        let event_json =
+            NostrService::sign_challenge_event(&nsec, "abc123", "cashu-marketplace").unwrap();
+
+        let verified = NostrService::verify_challenge_event(
+            &event_json,
+            "abc123",
+            "cashu-marketplace",
+            chrono::Duration::minutes(5),
+        )
+        .unwrap();
+
+        assert_eq!(verified, Some(npub));
+    }
+
+    #[test]
+    fn test_verify_challenge_event_rejects_mismatched_challenge() {
+        let (nsec, _npub) = NostrService::generate_keypair().unwrap();
+
+        let event_json =
+            NostrService::sign_challenge_event(&nsec, "abc123", "cashu-marketplace").unwrap();
+
+        let verified = NostrService::verify_challenge_event(
+            &event_json,
+            "different-challenge",
+            "cashu-marketplace",
+            chrono::Duration::minutes(5),
+        )
+        .unwrap();
+
+        assert_eq!(verified, None);
+    }
 }
@@ -0,0 +1,321 @@
+use nostr_sdk::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::config::Config;
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::models::WalletTransaction;
+use crate::services::{ConnectorRouter, DepositIndexer, ReconciliationService};
+
+/// NIP-47 request event kind
+const NWC_REQUEST_KIND: u16 = 23194;
+/// NIP-47 response event kind
+const NWC_RESPONSE_KIND: u16 = 23195;
+
+fn service_key_path(data_dir: &str) -> String {
+    format!("{}/service_nsec", data_dir)
+}
+
+/// One command + its JSON-RPC-style params, decrypted from an incoming
+/// NIP-47 request event's content
+#[derive(Debug, Deserialize)]
+struct NwcRequest {
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Debug, Serialize)]
+struct NwcError {
+    code: String,
+    message: String,
+}
+
+/// The encrypted reply published back as a kind 23195 event's content
+#[derive(Debug, Serialize)]
+struct NwcResponse {
+    result_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<NwcError>,
+}
+
+/// Nostr Wallet Connect (NIP-47) control surface for [`CashuService`]
+/// (routed through [`ConnectorRouter`]): lets any NWC-capable wallet app
+/// manage a user's marketplace balance over Nostr instead of only the web
+/// UI. The marketplace runs one long-lived service keypair; each connected
+/// app gets its own keypair, with only the app's public half persisted
+/// (see [`crate::models::NwcConnection`]) — the secret half is handed to
+/// the user once, in the connection URI, the same way a refresh token's
+/// plaintext never touches the database.
+///
+/// [`CashuService`]: crate::services::CashuService
+pub struct NwcService {
+    keys: Keys,
+    pub relay_url: String,
+}
+
+impl NwcService {
+    /// Load or generate the service's NIP-47 keypair
+    pub fn new(config: &Config) -> anyhow::Result<Self> {
+        std::fs::create_dir_all(&config.nwc.data_dir)?;
+        let path = service_key_path(&config.nwc.data_dir);
+
+        let keys = if let Ok(hex_secret) = std::fs::read_to_string(&path) {
+            let secret_bytes = hex::decode(hex_secret.trim())?;
+            Keys::new(SecretKey::from_slice(&secret_bytes)?)
+        } else {
+            let keys = Keys::generate();
+            std::fs::write(&path, hex::encode(keys.secret_key().secret_bytes()))?;
+            tracing::info!("Generated new NWC service keypair");
+            keys
+        };
+
+        Ok(Self {
+            keys,
+            relay_url: config.nwc.relay_url.clone(),
+        })
+    }
+
+    pub fn service_pubkey(&self) -> PublicKey {
+        self.keys.public_key()
+    }
+
+    /// Create a new app connection for `user_npub`, returning the
+    /// `nostr+walletconnect://` URI to hand to the NWC client. Only the
+    /// app's public key is stored; its secret key lives solely in the
+    /// returned URI.
+    pub async fn create_connection(
+        &self,
+        db: &Database,
+        user_npub: &str,
+        label: Option<&str>,
+    ) -> AppResult<String> {
+        let app_keys = Keys::generate();
+        let id = uuid::Uuid::new_v4().to_string();
+
+        sqlx::query(
+            "INSERT INTO nwc_connections (id, user_npub, app_pubkey, label, created_at) VALUES (?, ?, ?, ?, CURRENT_TIMESTAMP)",
+        )
+        .bind(&id)
+        .bind(user_npub)
+        .bind(app_keys.public_key().to_hex())
+        .bind(label)
+        .execute(db.pool())
+        .await?;
+
+        Ok(format!(
+            "nostr+walletconnect://{}?relay={}&secret={}",
+            self.keys.public_key().to_hex(),
+            self.relay_url,
+            hex::encode(app_keys.secret_key().secret_bytes()),
+        ))
+    }
+
+    /// Which user a connected app's pubkey belongs to, or `NotAuthorized`
+    /// if the connection doesn't exist or was revoked
+    async fn resolve_user(&self, db: &Database, app_pubkey: &PublicKey) -> AppResult<String> {
+        let row: Option<(String,)> = sqlx::query_as(
+            "SELECT user_npub FROM nwc_connections WHERE app_pubkey = ? AND revoked_at IS NULL",
+        )
+        .bind(app_pubkey.to_hex())
+        .fetch_optional(db.pool())
+        .await?;
+
+        row.map(|(npub,)| npub).ok_or(AppError::NotAuthorized)
+    }
+
+    /// Handle one incoming NIP-47 request event end to end: decrypt,
+    /// dispatch against the wallet, and build the signed response event
+    /// ready to publish back to the relay.
+    pub async fn handle_request(
+        &self,
+        db: &Database,
+        cashu: &ConnectorRouter,
+        deposit_indexer: &DepositIndexer,
+        request_event: &Event,
+    ) -> AppResult<Event> {
+        let user_npub = self.resolve_user(db, &request_event.pubkey).await?;
+
+        let plaintext = nip04::decrypt(
+            self.keys.secret_key(),
+            &request_event.pubkey,
+            &request_event.content,
+        )
+        .map_err(|e| AppError::Internal(format!("Failed to decrypt NWC request: {}", e)))?;
+
+        let request: NwcRequest = serde_json::from_str(&plaintext)
+            .map_err(|_| AppError::InvalidInput("Malformed NWC request".to_string()))?;
+
+        let response = self
+            .dispatch(db, cashu, deposit_indexer, &user_npub, request)
+            .await;
+
+        let response_json = serde_json::to_string(&response)
+            .map_err(|e| AppError::Internal(format!("Failed to serialize NWC response: {}", e)))?;
+
+        let encrypted = nip04::encrypt(
+            self.keys.secret_key(),
+            &request_event.pubkey,
+            response_json,
+        )
+        .map_err(|e| AppError::Internal(format!("Failed to encrypt NWC response: {}", e)))?;
+
+        let tags = vec![
+            Tag::parse(["e", &request_event.id.to_hex()])
+                .map_err(|e| AppError::Internal(e.to_string()))?,
+            Tag::parse(["p", &request_event.pubkey.to_hex()])
+                .map_err(|e| AppError::Internal(e.to_string()))?,
+        ];
+
+        EventBuilder::new(Kind::Custom(NWC_RESPONSE_KIND), encrypted)
+            .tags(tags)
+            .sign_with_keys(&self.keys)
+            .map_err(|e| AppError::Internal(format!("Failed to sign NWC response: {}", e)))
+    }
+
+    /// Relay subscription filter matching NIP-47 requests addressed to the
+    /// service's own pubkey, from `since` onward
+    pub fn request_filter(&self, since: Timestamp) -> Filter {
+        Filter::new()
+            .kind(Kind::Custom(NWC_REQUEST_KIND))
+            .pubkey(self.keys.public_key())
+            .since(since)
+    }
+
+    async fn dispatch(
+        &self,
+        db: &Database,
+        cashu: &ConnectorRouter,
+        deposit_indexer: &DepositIndexer,
+        user_npub: &str,
+        request: NwcRequest,
+    ) -> NwcResponse {
+        let result = match request.method.as_str() {
+            "make_invoice" => {
+                self.make_invoice(db, cashu, deposit_indexer, user_npub, &request.params)
+                    .await
+            }
+            "pay_invoice" => self.pay_invoice(db, cashu, user_npub, &request.params).await,
+            "get_balance" => self.get_balance(db, user_npub).await,
+            "list_transactions" => self.list_transactions(db, user_npub).await,
+            other => Err(AppError::InvalidInput(format!(
+                "Unsupported NWC method: {}",
+                other
+            ))),
+        };
+
+        match result {
+            Ok(result) => NwcResponse {
+                result_type: request.method,
+                result: Some(result),
+                error: None,
+            },
+            Err(e) => NwcResponse {
+                result_type: request.method,
+                result: None,
+                error: Some(NwcError {
+                    code: "INTERNAL".to_string(),
+                    message: e.to_string(),
+                }),
+            },
+        }
+    }
+
+    async fn make_invoice(
+        &self,
+        db: &Database,
+        cashu: &ConnectorRouter,
+        deposit_indexer: &DepositIndexer,
+        user_npub: &str,
+        params: &Value,
+    ) -> AppResult<Value> {
+        let amount_msat = params
+            .get("amount")
+            .and_then(Value::as_u64)
+            .ok_or_else(|| AppError::InvalidInput("missing amount".to_string()))?;
+        let amount_sats = (amount_msat / 1000).max(1);
+
+        let invoice = cashu.create_invoice(amount_sats).await?;
+        deposit_indexer
+            .track(
+                db,
+                &invoice.payment_hash,
+                user_npub,
+                amount_sats,
+                &invoice.connector_label,
+            )
+            .await?;
+
+        Ok(serde_json::json!({
+            "type": "incoming",
+            "invoice": invoice.payment_request,
+            "payment_hash": invoice.payment_hash,
+            "amount": amount_msat,
+            "created_at": chrono::Utc::now().timestamp(),
+            "expires_at": invoice.expires_at.timestamp(),
+        }))
+    }
+
+    async fn pay_invoice(
+        &self,
+        db: &Database,
+        cashu: &ConnectorRouter,
+        user_npub: &str,
+        params: &Value,
+    ) -> AppResult<Value> {
+        let invoice = params
+            .get("invoice")
+            .and_then(Value::as_str)
+            .ok_or_else(|| AppError::InvalidInput("missing invoice".to_string()))?;
+        let amount_sats = params
+            .get("amount")
+            .and_then(Value::as_u64)
+            .map(|msat| (msat / 1000).max(1))
+            .ok_or_else(|| AppError::InvalidInput("missing amount".to_string()))?;
+
+        let outcome =
+            ReconciliationService::withdraw(db, cashu, user_npub, amount_sats, invoice, None)
+                .await?;
+
+        Ok(serde_json::json!({
+            "preimage": outcome.preimage,
+            "fees_paid": outcome.fee_paid * 1000,
+        }))
+    }
+
+    async fn get_balance(&self, db: &Database, user_npub: &str) -> AppResult<Value> {
+        let (balance,): (i64,) = sqlx::query_as("SELECT wallet_balance FROM users WHERE npub = ?")
+            .bind(user_npub)
+            .fetch_one(db.pool())
+            .await?;
+
+        Ok(serde_json::json!({ "balance": balance * 1000 }))
+    }
+
+    async fn list_transactions(&self, db: &Database, user_npub: &str) -> AppResult<Value> {
+        let transactions: Vec<WalletTransaction> = sqlx::query_as(
+            "SELECT * FROM wallet_transactions WHERE user_npub = ? ORDER BY created_at DESC LIMIT 50",
+        )
+        .bind(user_npub)
+        .fetch_all(db.pool())
+        .await?;
+
+        let entries: Vec<Value> = transactions
+            .into_iter()
+            .map(|tx| {
+                serde_json::json!({
+                    "type": if tx.amount >= 0 { "incoming" } else { "outgoing" },
+                    "description": tx.description,
+                    "amount": tx.amount.unsigned_abs() * 1000,
+                    "reference_id": tx.reference_id,
+                    "created_at": tx.created_at.timestamp(),
+                })
+            })
+            .collect();
+
+        Ok(serde_json::json!({ "transactions": entries }))
+    }
+}
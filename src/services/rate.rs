@@ -0,0 +1,145 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, Duration, Utc};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use tokio::sync::RwLock;
+
+use crate::config::Config;
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+
+/// Sats per whole BTC, the fixed point every sats<->fiat conversion divides
+/// or multiplies through.
+const SATS_PER_BTC: i64 = 100_000_000;
+
+#[derive(Debug, Clone)]
+struct CachedRate {
+    rate: Decimal,
+    fetched_at: DateTime<Utc>,
+}
+
+/// Fetches and caches a BTC/fiat exchange rate so listings can be priced in
+/// fiat while the marketplace still settles exclusively in sats. Every
+/// freshly fetched rate is also recorded in `rate_history`, letting
+/// [`Self::historical_rate`] answer "what was the rate at purchase time"
+/// for receipts and completed-order views.
+pub struct RateService {
+    api_url: String,
+    currency: String,
+    cache_seconds: i64,
+    cached: RwLock<Option<CachedRate>>,
+}
+
+impl RateService {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            api_url: config.rate.api_url.clone(),
+            currency: config.rate.currency.clone(),
+            cache_seconds: config.rate.cache_seconds as i64,
+            cached: RwLock::new(None),
+        }
+    }
+
+    pub fn currency(&self) -> &str {
+        &self.currency
+    }
+
+    /// Current BTC/`currency` rate (fiat per whole BTC), refetching only
+    /// once the cached value is older than `cache_seconds`.
+    pub async fn current_rate(&self, db: &Database) -> AppResult<Decimal> {
+        if let Some(cached) = self.cached.read().await.as_ref() {
+            if Utc::now() - cached.fetched_at < Duration::seconds(self.cache_seconds) {
+                return Ok(cached.rate);
+            }
+        }
+
+        let rate = self.fetch_rate().await?;
+
+        sqlx::query(
+            "INSERT INTO rate_history (id, currency, rate, fetched_at) VALUES (?, ?, ?, CURRENT_TIMESTAMP)",
+        )
+        .bind(uuid::Uuid::new_v4().to_string())
+        .bind(&self.currency)
+        .bind(rate.to_string())
+        .execute(db.pool())
+        .await?;
+
+        *self.cached.write().await = Some(CachedRate {
+            rate,
+            fetched_at: Utc::now(),
+        });
+
+        Ok(rate)
+    }
+
+    async fn fetch_rate(&self) -> AppResult<Decimal> {
+        let response = reqwest::get(&self.api_url).await.map_err(|e| {
+            AppError::Internal(format!("Failed to fetch BTC/{} rate: {}", self.currency, e))
+        })?;
+
+        let body: serde_json::Value = response.json().await.map_err(|e| {
+            AppError::Internal(format!("Failed to parse rate response: {}", e))
+        })?;
+
+        let price = body
+            .get("bitcoin")
+            .and_then(|b| b.get(&self.currency))
+            .and_then(|v| v.as_f64())
+            .ok_or_else(|| AppError::Internal("Rate response missing expected field".to_string()))?;
+
+        Decimal::from_str(&price.to_string())
+            .map_err(|_| AppError::Internal("Rate response was not a finite number".to_string()))
+    }
+
+    /// The closest rate recorded at or before `at`, for showing a receipt
+    /// or completed order at the value it actually had at purchase time.
+    /// `None` if no rate had been fetched yet by `at`.
+    pub async fn historical_rate(
+        &self,
+        db: &Database,
+        at: DateTime<Utc>,
+    ) -> AppResult<Option<Decimal>> {
+        let row: Option<(String,)> = sqlx::query_as(
+            "SELECT rate FROM rate_history WHERE currency = ? AND fetched_at <= ? ORDER BY fetched_at DESC LIMIT 1",
+        )
+        .bind(&self.currency)
+        .bind(at)
+        .fetch_optional(db.pool())
+        .await?;
+
+        Ok(row.and_then(|(rate,)| Decimal::from_str(&rate).ok()))
+    }
+
+    /// Convert `sats` to a fiat amount at `rate` (fiat per whole BTC),
+    /// guarding every division/multiplication against overflow instead of
+    /// panicking.
+    pub fn sats_to_fiat(sats: i64, rate: Decimal) -> AppResult<Decimal> {
+        let fiat_per_sat = rate
+            .checked_div(Decimal::from(SATS_PER_BTC))
+            .ok_or(AppError::Internal("Rate conversion overflowed".to_string()))?;
+
+        Decimal::from(sats)
+            .checked_mul(fiat_per_sat)
+            .ok_or(AppError::Internal("Rate conversion overflowed".to_string()))
+    }
+
+    /// Convert a fiat amount to whole sats at `rate` (fiat per whole BTC).
+    pub fn fiat_to_sats(fiat: Decimal, rate: Decimal) -> AppResult<i64> {
+        if rate.is_zero() {
+            return Err(AppError::Internal("Rate cannot be zero".to_string()));
+        }
+
+        let btc = fiat
+            .checked_div(rate)
+            .ok_or(AppError::Internal("Rate conversion overflowed".to_string()))?;
+
+        let sats = btc
+            .checked_mul(Decimal::from(SATS_PER_BTC))
+            .ok_or(AppError::Internal("Rate conversion overflowed".to_string()))?;
+
+        sats.round()
+            .to_i64()
+            .ok_or(AppError::Internal("Rate conversion produced an out-of-range amount".to_string()))
+    }
+}
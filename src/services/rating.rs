@@ -0,0 +1,140 @@
+use serde::Serialize;
+
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::models::Order;
+
+/// Number of discrete star values a rating histogram tracks (1 through 5).
+const RATING_BUCKETS: usize = 5;
+
+/// Per-seller rating summary: the raw average/count alongside a
+/// Bayesian-adjusted score that regresses toward `prior_mean` until
+/// enough ratings have accumulated to outweigh it.
+#[derive(Debug, Clone, Serialize)]
+pub struct SellerReputation {
+    pub npub: String,
+    pub rating_count: i64,
+    pub avg_rating: Option<f64>,
+    /// `(avg_rating * rating_count + prior_mean * prior_weight) / (rating_count + prior_weight)`.
+    /// Equals `prior_mean` at zero ratings and converges to `avg_rating` as
+    /// `rating_count` grows past `prior_weight`.
+    pub adjusted_score: f64,
+    /// Count of ratings at each star value, indexed `[1-star, 2-star, 3-star, 4-star, 5-star]`.
+    pub histogram: [i64; RATING_BUCKETS],
+}
+
+/// Buyer ratings of completed orders, and the seller reputation derived
+/// from them.
+pub struct RatingService;
+
+impl RatingService {
+    /// Record `buyer_npub`'s rating of `order` and recompute the seller's
+    /// `seller_stats.avg_rating` in the same transaction. Fails unless
+    /// `buyer_npub` is the order's buyer, the order is `Completed`, and it
+    /// hasn't already been rated (`order_ratings.order_id` is also a
+    /// primary key, so this is a friendlier pre-check ahead of that
+    /// constraint rather than a replacement for it).
+    pub async fn rate_order(
+        db: &Database,
+        order: &Order,
+        buyer_npub: &str,
+        rating: i32,
+        comment: Option<String>,
+    ) -> AppResult<()> {
+        if order.buyer_npub != buyer_npub {
+            return Err(AppError::NotAuthorized);
+        }
+
+        if order.status_enum() != crate::models::OrderStatus::Completed {
+            return Err(AppError::OrderNotRatable);
+        }
+
+        let rating = rating.clamp(1, 5);
+
+        let mut db_tx = db.pool().begin().await?;
+
+        let already_rated: Option<(String,)> =
+            sqlx::query_as("SELECT order_id FROM order_ratings WHERE order_id = ?")
+                .bind(&order.id)
+                .fetch_optional(&mut *db_tx)
+                .await?;
+
+        if already_rated.is_some() {
+            return Err(AppError::OrderAlreadyRated);
+        }
+
+        sqlx::query(
+            "INSERT INTO order_ratings (order_id, buyer_npub, seller_npub, rating, comment, created_at) VALUES (?, ?, ?, ?, ?, CURRENT_TIMESTAMP)",
+        )
+        .bind(&order.id)
+        .bind(buyer_npub)
+        .bind(&order.seller_npub)
+        .bind(rating)
+        .bind(&comment)
+        .execute(&mut *db_tx)
+        .await?;
+
+        let (avg_rating,): (Option<f64>,) =
+            sqlx::query_as("SELECT AVG(rating) FROM order_ratings WHERE seller_npub = ?")
+                .bind(&order.seller_npub)
+                .fetch_one(&mut *db_tx)
+                .await?;
+
+        sqlx::query(
+            "INSERT INTO seller_stats (npub, avg_rating, updated_at) VALUES (?, ?, CURRENT_TIMESTAMP)
+             ON CONFLICT(npub) DO UPDATE SET avg_rating = excluded.avg_rating, updated_at = CURRENT_TIMESTAMP",
+        )
+        .bind(&order.seller_npub)
+        .bind(avg_rating)
+        .execute(&mut *db_tx)
+        .await?;
+
+        db_tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Build `seller_npub`'s rating histogram and Bayesian-adjusted score
+    /// from every `order_ratings` row on file, using `prior_mean`/
+    /// `prior_weight` as the regression-to-the-mean prior (see
+    /// [`crate::config::Config::rating_prior_mean`]/`rating_prior_weight`).
+    pub async fn reputation(
+        db: &Database,
+        seller_npub: &str,
+        prior_mean: f64,
+        prior_weight: f64,
+    ) -> AppResult<SellerReputation> {
+        let ratings: Vec<(i32,)> =
+            sqlx::query_as("SELECT rating FROM order_ratings WHERE seller_npub = ?")
+                .bind(seller_npub)
+                .fetch_all(db.pool())
+                .await?;
+
+        let rating_count = ratings.len() as i64;
+        let mut histogram = [0i64; RATING_BUCKETS];
+        let mut total = 0i64;
+        for (rating,) in &ratings {
+            let bucket = (rating.clamp(1, 5) - 1) as usize;
+            histogram[bucket] += 1;
+            total += *rating as i64;
+        }
+
+        let avg_rating = if rating_count > 0 {
+            Some(total as f64 / rating_count as f64)
+        } else {
+            None
+        };
+
+        let adjusted_score = (avg_rating.unwrap_or(0.0) * rating_count as f64
+            + prior_mean * prior_weight)
+            / (rating_count as f64 + prior_weight);
+
+        Ok(SellerReputation {
+            npub: seller_npub.to_string(),
+            rating_count,
+            avg_rating,
+            adjusted_score,
+            histogram,
+        })
+    }
+}
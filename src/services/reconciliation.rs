@@ -0,0 +1,370 @@
+use chrono::{DateTime, Duration, Utc};
+
+use crate::db::Database;
+use crate::error::AppResult;
+use crate::services::{ConnectorRouter, LedgerService, WithdrawalResult, ACCOUNT_MINT_FLOAT};
+
+/// Account a given withdrawal's debited sats sit in between being pulled
+/// from the user and the Lightning melt settling, mirroring
+/// `escrow_hold_account`.
+fn withdrawal_hold_account(withdrawal_id: &str) -> String {
+    format!("withdrawal-hold:{}", withdrawal_id)
+}
+
+/// How long a withdrawal can sit `pending` before the background task
+/// retries the melt and, failing that, reverses it back to the user rather
+/// than leaving them debited indefinitely.
+const STUCK_WITHDRAWAL_MINUTES: i64 = 15;
+
+/// Result of a settled withdrawal, handed back to the caller once the melt
+/// is confirmed paid.
+pub struct WithdrawalOutcome {
+    pub preimage: String,
+    pub fee_paid: u64,
+}
+
+/// A tracked deposit identifier the indexer hasn't credited yet, with how
+/// long it's been outstanding.
+#[derive(Debug, Clone)]
+pub struct UnsettledDeposit {
+    pub identifier: String,
+    pub user_npub: String,
+    pub amount_sats: i64,
+    pub age_minutes: i64,
+}
+
+/// A withdrawal still awaiting melt confirmation, with how long it's been
+/// outstanding.
+#[derive(Debug, Clone)]
+pub struct StuckWithdrawal {
+    pub id: String,
+    pub user_npub: String,
+    pub amount_sats: i64,
+    pub age_minutes: i64,
+}
+
+/// Snapshot of everything that doesn't look settled, for an operator (or
+/// automated monitor) to act on.
+#[derive(Debug, Clone, Default)]
+pub struct ReconciliationReport {
+    pub unsettled_deposits: Vec<UnsettledDeposit>,
+    pub stuck_withdrawals: Vec<StuckWithdrawal>,
+}
+
+/// Reconciles what the payment connector actually settled against what the
+/// ledger recorded, and drives withdrawals through a two-phase
+/// hold-then-settle flow: a withdrawal debits the user into a per-withdrawal
+/// hold account immediately, and is only finalized into the mint float (or
+/// reversed back to the user) once the melt's outcome is known. This closes
+/// the window `wallet::withdraw` used to leave open where a failed or
+/// still-pending Lightning payment left the user debited for nothing.
+pub struct ReconciliationService;
+
+impl ReconciliationService {
+    /// Withdraw `amount_sats` to `invoice` for `user_npub` through the
+    /// two-phase flow described on [`ReconciliationService`]. `label` tags
+    /// the resulting `wallet_transactions` row (e.g. with an order
+    /// reference) so a seller payout can be reconciled by
+    /// [`LedgerService::get_transactions_by_label`] later.
+    pub async fn withdraw(
+        db: &Database,
+        cashu: &ConnectorRouter,
+        user_npub: &str,
+        amount_sats: u64,
+        invoice: &str,
+        label: Option<&str>,
+    ) -> AppResult<WithdrawalOutcome> {
+        let withdrawal_id = uuid::Uuid::new_v4().to_string();
+        let hold_account = withdrawal_hold_account(&withdrawal_id);
+
+        // Quote before touching the ledger, so a caller that can't even
+        // get a quote doesn't have the user's balance held for nothing.
+        // The quote id is persisted below so a crash before this attempt's
+        // outcome is recorded — or a renewed attempt in
+        // `retry_stuck_withdrawals` — can check this exact quote rather
+        // than paying the invoice again under a fresh one.
+        let (quote_id, fee_reserve) = cashu.quote_withdrawal(invoice).await?;
+
+        // Phase 1: hold the sats and record the pending withdrawal before
+        // attempting anything that talks to the network.
+        let mut db_tx = db.pool().begin().await?;
+        LedgerService::post(
+            &mut db_tx,
+            user_npub,
+            &hold_account,
+            amount_sats as i64,
+            "withdraw",
+            Some(&withdrawal_id),
+        )
+        .await?;
+
+        sqlx::query(
+            "INSERT INTO pending_withdrawals (id, user_npub, amount_sats, invoice, melt_quote_id, fee_reserve_sats, status, attempts, created_at) VALUES (?, ?, ?, ?, ?, ?, 'pending', 1, CURRENT_TIMESTAMP)",
+        )
+        .bind(&withdrawal_id)
+        .bind(user_npub)
+        .bind(amount_sats as i64)
+        .bind(invoice)
+        .bind(&quote_id)
+        .bind(fee_reserve as i64)
+        .execute(&mut *db_tx)
+        .await?;
+
+        let (new_balance,): (i64,) =
+            sqlx::query_as("SELECT wallet_balance FROM users WHERE npub = ?")
+                .bind(user_npub)
+                .fetch_one(&mut *db_tx)
+                .await?;
+
+        sqlx::query(
+            "INSERT INTO wallet_transactions (id, user_npub, transaction_type, amount, balance_after, reference_id, description, status, label, created_at) VALUES (?, ?, 'withdraw', ?, ?, ?, 'Lightning withdrawal', 'pending', ?, CURRENT_TIMESTAMP)",
+        )
+        .bind(&withdrawal_id)
+        .bind(user_npub)
+        .bind(-(amount_sats as i64))
+        .bind(new_balance)
+        .bind(&withdrawal_id)
+        .bind(label)
+        .execute(&mut *db_tx)
+        .await?;
+
+        db_tx.commit().await?;
+
+        // Phase 2: attempt the melt, then finalize or reverse based on the
+        // outcome.
+        match cashu.execute_withdrawal(&quote_id, amount_sats, fee_reserve).await {
+            Ok(result) => {
+                Self::finalize(
+                    db,
+                    &withdrawal_id,
+                    &hold_account,
+                    amount_sats as i64,
+                    result.fee_paid as i64,
+                )
+                .await?;
+                Ok(WithdrawalOutcome {
+                    preimage: result.preimage,
+                    fee_paid: result.fee_paid,
+                })
+            }
+            Err(e) => {
+                Self::reverse(db, &withdrawal_id, user_npub, &hold_account, amount_sats as i64)
+                    .await?;
+                Err(e)
+            }
+        }
+    }
+
+    /// Re-attempt every withdrawal still `pending` after
+    /// [`STUCK_WITHDRAWAL_MINUTES`] — most withdrawals settle on the first
+    /// attempt, but a crash between the hold and the melt call can leave one
+    /// stuck forever without this. A renewed failure reverses the hold back
+    /// to the user instead of leaving them debited indefinitely.
+    pub async fn retry_stuck_withdrawals(db: &Database, cashu: &ConnectorRouter) -> AppResult<u32> {
+        let pending: Vec<(String, String, i64, String, Option<String>, i64, DateTime<Utc>)> = sqlx::query_as(
+            "SELECT id, user_npub, amount_sats, invoice, melt_quote_id, fee_reserve_sats, created_at FROM pending_withdrawals WHERE status = 'pending'",
+        )
+        .fetch_all(db.pool())
+        .await?;
+
+        let cutoff = Utc::now() - Duration::minutes(STUCK_WITHDRAWAL_MINUTES);
+        let mut resolved = 0u32;
+
+        for (id, user_npub, amount_sats, invoice, melt_quote_id, fee_reserve_sats, created_at) in pending {
+            if created_at > cutoff {
+                continue;
+            }
+
+            let hold_account = withdrawal_hold_account(&id);
+            sqlx::query("UPDATE pending_withdrawals SET attempts = attempts + 1 WHERE id = ?")
+                .bind(&id)
+                .execute(db.pool())
+                .await?;
+
+            // The stuck withdrawal's own melt quote may have actually
+            // settled — a crash between the hold committing and the
+            // outcome being recorded is exactly the gap this retry exists
+            // to close, and it can't be told apart from "nothing was ever
+            // sent" without asking the mint. Check before paying again.
+            let already_paid = match &melt_quote_id {
+                Some(quote_id) => cashu.check_withdrawal_paid(quote_id).await.unwrap_or(false),
+                None => false,
+            };
+
+            if already_paid {
+                Self::finalize(db, &id, &hold_account, amount_sats, fee_reserve_sats).await?;
+                tracing::info!(
+                    "settled previously-stuck withdrawal {} (quote was already paid)",
+                    id
+                );
+                resolved += 1;
+                continue;
+            }
+
+            match Self::requote_and_pay(cashu, &invoice, amount_sats as u64, &id, db).await {
+                Ok(result) => {
+                    Self::finalize(db, &id, &hold_account, amount_sats, result.fee_paid as i64)
+                        .await?;
+                    tracing::info!("settled previously-stuck withdrawal {}", id);
+                }
+                Err(e) => {
+                    Self::reverse(db, &id, &user_npub, &hold_account, amount_sats).await?;
+                    tracing::warn!(
+                        "reversed stuck withdrawal {} after retry failed: {}",
+                        id,
+                        e
+                    );
+                }
+            }
+            resolved += 1;
+        }
+
+        Ok(resolved)
+    }
+
+    /// Quote and pay `invoice` fresh for a retried withdrawal, now that
+    /// [`Self::retry_stuck_withdrawals`] has confirmed the original quote
+    /// (if any) didn't pay — the original quote may also simply have
+    /// expired, which is fine since we already know it wasn't settled.
+    /// Persists the new quote id so a further crash can be checked the
+    /// same way.
+    async fn requote_and_pay(
+        cashu: &ConnectorRouter,
+        invoice: &str,
+        amount_sats: u64,
+        withdrawal_id: &str,
+        db: &Database,
+    ) -> AppResult<WithdrawalResult> {
+        let (quote_id, fee_reserve) = cashu.quote_withdrawal(invoice).await?;
+
+        sqlx::query(
+            "UPDATE pending_withdrawals SET melt_quote_id = ?, fee_reserve_sats = ? WHERE id = ?",
+        )
+        .bind(&quote_id)
+        .bind(fee_reserve as i64)
+        .bind(withdrawal_id)
+        .execute(db.pool())
+        .await?;
+
+        cashu
+            .execute_withdrawal(&quote_id, amount_sats, fee_reserve)
+            .await
+    }
+
+    /// Everything that doesn't look settled: deposits the indexer hasn't
+    /// credited yet, and withdrawals still awaiting melt confirmation.
+    pub async fn report(db: &Database) -> AppResult<ReconciliationReport> {
+        let deposits: Vec<(String, String, i64, DateTime<Utc>)> = sqlx::query_as(
+            "SELECT identifier, user_npub, amount_sats, created_at FROM tracked_deposits WHERE credited = FALSE ORDER BY created_at ASC",
+        )
+        .fetch_all(db.pool())
+        .await?;
+
+        let withdrawals: Vec<(String, String, i64, DateTime<Utc>)> = sqlx::query_as(
+            "SELECT id, user_npub, amount_sats, created_at FROM pending_withdrawals WHERE status = 'pending' ORDER BY created_at ASC",
+        )
+        .fetch_all(db.pool())
+        .await?;
+
+        let now = Utc::now();
+
+        let unsettled_deposits = deposits
+            .into_iter()
+            .map(|(identifier, user_npub, amount_sats, created_at)| UnsettledDeposit {
+                identifier,
+                user_npub,
+                amount_sats,
+                age_minutes: (now - created_at).num_minutes(),
+            })
+            .collect();
+
+        let stuck_withdrawals = withdrawals
+            .into_iter()
+            .map(|(id, user_npub, amount_sats, created_at)| StuckWithdrawal {
+                id,
+                user_npub,
+                amount_sats,
+                age_minutes: (now - created_at).num_minutes(),
+            })
+            .collect();
+
+        Ok(ReconciliationReport {
+            unsettled_deposits,
+            stuck_withdrawals,
+        })
+    }
+
+    /// Move a withdrawal's held sats into the mint float and mark it
+    /// settled — the melt is confirmed paid. `fee_sats` is only known once
+    /// the melt settles, so it's recorded here rather than at hold time.
+    async fn finalize(
+        db: &Database,
+        withdrawal_id: &str,
+        hold_account: &str,
+        amount_sats: i64,
+        fee_sats: i64,
+    ) -> AppResult<()> {
+        let mut db_tx = db.pool().begin().await?;
+        LedgerService::post(
+            &mut db_tx,
+            hold_account,
+            ACCOUNT_MINT_FLOAT,
+            amount_sats,
+            "withdraw",
+            Some(withdrawal_id),
+        )
+        .await?;
+
+        sqlx::query(
+            "UPDATE pending_withdrawals SET status = 'settled', settled_at = CURRENT_TIMESTAMP WHERE id = ?",
+        )
+        .bind(withdrawal_id)
+        .execute(&mut *db_tx)
+        .await?;
+        sqlx::query(
+            "UPDATE wallet_transactions SET status = 'settled', fee_sats = ? WHERE reference_id = ?",
+        )
+        .bind(fee_sats)
+        .bind(withdrawal_id)
+        .execute(&mut *db_tx)
+        .await?;
+
+        db_tx.commit().await?;
+        Ok(())
+    }
+
+    /// Move a withdrawal's held sats back to the user and mark it reversed
+    /// — the melt failed, so the user was never actually debited for good.
+    async fn reverse(
+        db: &Database,
+        withdrawal_id: &str,
+        user_npub: &str,
+        hold_account: &str,
+        amount_sats: i64,
+    ) -> AppResult<()> {
+        let mut db_tx = db.pool().begin().await?;
+        LedgerService::post(
+            &mut db_tx,
+            hold_account,
+            user_npub,
+            amount_sats,
+            "withdraw_reversal",
+            Some(withdrawal_id),
+        )
+        .await?;
+
+        sqlx::query(
+            "UPDATE pending_withdrawals SET status = 'reversed', settled_at = CURRENT_TIMESTAMP WHERE id = ?",
+        )
+        .bind(withdrawal_id)
+        .execute(&mut *db_tx)
+        .await?;
+        sqlx::query("UPDATE wallet_transactions SET status = 'reversed' WHERE reference_id = ?")
+            .bind(withdrawal_id)
+            .execute(&mut *db_tx)
+            .await?;
+
+        db_tx.commit().await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,139 @@
+use chrono::{Duration, Utc};
+use sha2::{Digest, Sha256};
+
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::models::RefreshToken;
+
+/// How long an issued refresh token stays valid if never used.
+const REFRESH_TOKEN_DAYS: i64 = 30;
+
+/// Refresh-token rotation backing long-lived sessions: a logged-in user
+/// gets a short-lived access session (see `routes::auth`) plus a
+/// long-lived, single-use refresh token. Each refresh issues a new access
+/// session and rotates the refresh token, so a stolen-but-unused token is
+/// only ever good for one more refresh before [`Self::rotate`] detects the
+/// reuse and revokes the whole token family.
+pub struct RefreshTokenService;
+
+impl RefreshTokenService {
+    /// Issue a fresh refresh token starting a brand new family (a new
+    /// login), returning the plaintext token to hand to the client.
+    pub async fn issue(db: &Database, user_npub: &str) -> AppResult<String> {
+        let family_id = uuid::Uuid::new_v4().to_string();
+        Self::insert(db, user_npub, &family_id, None).await
+    }
+
+    /// Validate `token`, rotate it into a new token in the same family,
+    /// and return `(user_npub, new_token)`. Reuse of an already-rotated
+    /// token revokes the whole family and returns
+    /// [`AppError::RefreshTokenReused`].
+    pub async fn rotate(db: &Database, token: &str) -> AppResult<(String, String)> {
+        let token_hash = Self::hash(token);
+
+        let record: RefreshToken =
+            sqlx::query_as("SELECT * FROM refresh_tokens WHERE token_hash = ?")
+                .bind(&token_hash)
+                .fetch_optional(db.pool())
+                .await?
+                .ok_or(AppError::InvalidRefreshToken)?;
+
+        if record.is_revoked() {
+            Self::revoke_family(db, &record.family_id).await?;
+            return Err(AppError::RefreshTokenReused);
+        }
+
+        if record.is_expired() {
+            return Err(AppError::InvalidRefreshToken);
+        }
+
+        sqlx::query("UPDATE refresh_tokens SET revoked_at = CURRENT_TIMESTAMP WHERE id = ?")
+            .bind(&record.id)
+            .execute(db.pool())
+            .await?;
+
+        let new_token =
+            Self::insert(db, &record.user_npub, &record.family_id, Some(&record.id)).await?;
+
+        Ok((record.user_npub, new_token))
+    }
+
+    /// Revoke every still-valid token in `family_id` — called on reuse
+    /// detection, and reusable for an explicit logout-everywhere.
+    pub async fn revoke_family(db: &Database, family_id: &str) -> AppResult<()> {
+        sqlx::query(
+            "UPDATE refresh_tokens SET revoked_at = CURRENT_TIMESTAMP WHERE family_id = ? AND revoked_at IS NULL",
+        )
+        .bind(family_id)
+        .execute(db.pool())
+        .await?;
+        Ok(())
+    }
+
+    /// Revoke every refresh token family belonging to a user, regardless
+    /// of which login issued it — used by admins to kill all of a
+    /// disputed user's sessions.
+    pub async fn revoke_all_for_user(db: &Database, user_npub: &str) -> AppResult<()> {
+        sqlx::query(
+            "UPDATE refresh_tokens SET revoked_at = CURRENT_TIMESTAMP WHERE user_npub = ? AND revoked_at IS NULL",
+        )
+        .bind(user_npub)
+        .execute(db.pool())
+        .await?;
+        Ok(())
+    }
+
+    /// Revoke the family a specific (still-valid) refresh token belongs
+    /// to, without first validating it as a rotation — used by logout.
+    pub async fn revoke_by_token(db: &Database, token: &str) -> AppResult<()> {
+        let token_hash = Self::hash(token);
+
+        let family: Option<(String,)> =
+            sqlx::query_as("SELECT family_id FROM refresh_tokens WHERE token_hash = ?")
+                .bind(&token_hash)
+                .fetch_optional(db.pool())
+                .await?;
+
+        if let Some((family_id,)) = family {
+            Self::revoke_family(db, &family_id).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn insert(
+        db: &Database,
+        user_npub: &str,
+        family_id: &str,
+        rotated_from: Option<&str>,
+    ) -> AppResult<String> {
+        let token = Self::generate_token();
+        let token_hash = Self::hash(&token);
+        let id = uuid::Uuid::new_v4().to_string();
+        let expires_at = Utc::now() + Duration::days(REFRESH_TOKEN_DAYS);
+
+        sqlx::query(
+            "INSERT INTO refresh_tokens (id, user_npub, token_hash, family_id, rotated_from, expires_at, created_at) VALUES (?, ?, ?, ?, ?, ?, CURRENT_TIMESTAMP)",
+        )
+        .bind(&id)
+        .bind(user_npub)
+        .bind(&token_hash)
+        .bind(family_id)
+        .bind(rotated_from)
+        .bind(expires_at)
+        .execute(db.pool())
+        .await?;
+
+        Ok(token)
+    }
+
+    fn generate_token() -> String {
+        use rand::Rng;
+        let bytes: [u8; 32] = rand::thread_rng().gen();
+        hex::encode(bytes)
+    }
+
+    fn hash(token: &str) -> String {
+        hex::encode(Sha256::digest(token.as_bytes()))
+    }
+}
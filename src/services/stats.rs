@@ -0,0 +1,150 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Datelike, Duration, Utc};
+use serde::Serialize;
+
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+
+/// Granularity a [`StatsService::time_series`] call groups activity by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatsBucket {
+    Day,
+    Week,
+    Month,
+}
+
+impl std::str::FromStr for StatsBucket {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "day" => Ok(StatsBucket::Day),
+            "week" => Ok(StatsBucket::Week),
+            "month" => Ok(StatsBucket::Month),
+            other => Err(AppError::InvalidInput(format!(
+                "Unknown stats bucket '{}'",
+                other
+            ))),
+        }
+    }
+}
+
+impl StatsBucket {
+    /// Key identifying which bucket `at` falls into, lexically sortable so
+    /// a `BTreeMap` keyed by it iterates in chronological order.
+    fn key(self, at: DateTime<Utc>) -> String {
+        match self {
+            StatsBucket::Day => at.format("%Y-%m-%d").to_string(),
+            StatsBucket::Week => {
+                let week = at.iso_week();
+                format!("{}-W{:02}", week.year(), week.week())
+            }
+            StatsBucket::Month => at.format("%Y-%m").to_string(),
+        }
+    }
+}
+
+/// One bucketed row of marketplace activity, ready to hand to a chart or
+/// render as a table.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StatsPoint {
+    pub bucket: String,
+    pub orders_created: i64,
+    pub gross_merchandise_value: i64,
+    pub fees_collected: i64,
+    pub escrow_held: i64,
+    pub escrow_released: i64,
+    pub disputes_opened: i64,
+    pub disputes_resolved: i64,
+}
+
+pub struct StatsService;
+
+impl StatsService {
+    /// Time-bucketed marketplace activity over the trailing `range_days`,
+    /// grouped by `bucket`. Issues six queries — orders joined to their
+    /// escrow amount, paid checkout sessions, held and released
+    /// escrows, opened and resolved disputes — and buckets each in Rust via
+    /// `chrono` rather than a SQL
+    /// `GROUP BY` on a truncated timestamp, consistent with how
+    /// [`crate::services::ReconciliationService`] already does its date
+    /// arithmetic against the plain `DateTime<Utc>` columns sqlx hands
+    /// back, instead of leaning on SQLite's string-based date functions.
+    pub async fn time_series(
+        db: &Database,
+        bucket: StatsBucket,
+        range_days: i64,
+    ) -> AppResult<Vec<StatsPoint>> {
+        let cutoff = Utc::now() - Duration::days(range_days);
+        let mut points: BTreeMap<String, StatsPoint> = BTreeMap::new();
+
+        let orders: Vec<(DateTime<Utc>, i64)> = sqlx::query_as(
+            "SELECT o.created_at, e.amount FROM orders o JOIN escrows e ON o.escrow_id = e.id WHERE o.created_at > ?",
+        )
+        .bind(cutoff)
+        .fetch_all(db.pool())
+        .await?;
+        for (created_at, amount) in orders {
+            let point = points.entry(bucket.key(created_at)).or_default();
+            point.orders_created += 1;
+            point.gross_merchandise_value += amount;
+        }
+
+        let paid_checkouts: Vec<(DateTime<Utc>, i64)> = sqlx::query_as(
+            "SELECT paid_at, fee_amount FROM checkout_sessions WHERE status = 'paid' AND paid_at IS NOT NULL AND paid_at > ?",
+        )
+        .bind(cutoff)
+        .fetch_all(db.pool())
+        .await?;
+        for (paid_at, fee_amount) in paid_checkouts {
+            points.entry(bucket.key(paid_at)).or_default().fees_collected += fee_amount;
+        }
+
+        let held: Vec<(DateTime<Utc>, i64)> =
+            sqlx::query_as("SELECT created_at, amount FROM escrows WHERE created_at > ?")
+                .bind(cutoff)
+                .fetch_all(db.pool())
+                .await?;
+        for (created_at, amount) in held {
+            points.entry(bucket.key(created_at)).or_default().escrow_held += amount;
+        }
+
+        let released: Vec<(DateTime<Utc>, i64)> = sqlx::query_as(
+            "SELECT resolved_at, amount FROM escrows WHERE status = 'released' AND resolved_at IS NOT NULL AND resolved_at > ?",
+        )
+        .bind(cutoff)
+        .fetch_all(db.pool())
+        .await?;
+        for (resolved_at, amount) in released {
+            points.entry(bucket.key(resolved_at)).or_default().escrow_released += amount;
+        }
+
+        let disputes_opened: Vec<(DateTime<Utc>,)> =
+            sqlx::query_as("SELECT created_at FROM disputes WHERE created_at > ?")
+                .bind(cutoff)
+                .fetch_all(db.pool())
+                .await?;
+        for (created_at,) in disputes_opened {
+            points.entry(bucket.key(created_at)).or_default().disputes_opened += 1;
+        }
+
+        let disputes_resolved: Vec<(DateTime<Utc>,)> = sqlx::query_as(
+            "SELECT resolved_at FROM disputes WHERE status = 'resolved' AND resolved_at IS NOT NULL AND resolved_at > ?",
+        )
+        .bind(cutoff)
+        .fetch_all(db.pool())
+        .await?;
+        for (resolved_at,) in disputes_resolved {
+            points.entry(bucket.key(resolved_at)).or_default().disputes_resolved += 1;
+        }
+
+        Ok(points
+            .into_iter()
+            .map(|(key, mut point)| {
+                point.bucket = key;
+                point
+            })
+            .collect())
+    }
+}